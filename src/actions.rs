@@ -58,25 +58,49 @@ actions! {
   [FinishPlayingCard;],
   [EndTurn;],
   [StartMonsterTurn (pub usize);],
-  [DoMonsterIntent (pub usize);],
+  [DoMonsterIntent (pub usize, pub usize);],
   [FinishMonsterTurn (pub usize);],
+  [StartOfTurnPostDraw (pub CreatureIndex);],
   [ChooseMonsterIntent (pub usize);],
 
   // used by many effects
   [DamageAction {pub target: CreatureIndex, pub info: DamageInfo}],
   [DamageAllEnemiesAction {pub damage_type: DamageType, pub damage: i32}],
   [AttackDamageRandomEnemyAction {pub damage: i32}],
+  [DamageRandomEnemyAction {pub damage: i32, pub damage_type: DamageType}],
   [DrawCardRandom;],
   [DrawCards (pub i32);],
+  [DrawToHandSize (pub i32);],
+  [ShuffleDiscardIntoDrawPile;],
   [ApplyPowerAction {pub source: CreatureIndex, pub target: CreatureIndex, pub power_id: PowerId, pub amount: i32}],
   [ReducePowerAction {pub target: CreatureIndex, pub power_id: PowerId, pub amount: i32}],
   [RemoveSpecificPowerAction {pub target: CreatureIndex, pub power_id: PowerId}],
   [DiscardNewCard (pub SingleCard);],
+  [DrawPileNewCard (pub SingleCard);],
+  [HandNewCard (pub SingleCard);],
+  [DiscardToHand (pub SingleCard);],
+  [AddCardToPile {pub card: SingleCard, pub pile: PileLocation, pub position: CardPosition}],
+  [HandRandomCardAction {pub class: CardClass, pub card_type: CardType, pub cost_for_turn: Option<i32>}],
+  [ChooseCardsAction {pub choice_type: CardChoiceType, pub count: i32, pub any_number: bool}],
+  [ChooseCard {pub choice_type: CardChoiceType, pub card_index: usize}],
+  [StopChoosingCards;],
+  [ExhaustRandomCardAction;],
   [GainBlockAction {pub creature_index: CreatureIndex, pub amount: i32}],
   [GainEnergyAction (pub i32);],
+  [ChangeStance (pub Stance);],
+  [ChannelOrb (pub OrbType);],
+  [EvokeOrb;],
+  [DarkImpulse;],
+  [DelayAction {pub turns: i32, pub action: Box<DynAction>}],
+  [RepeatNextCardsAction {pub card_type: CardType, pub count: u32}],
+  [GainGoldAction (pub i32);],
+  [LoseGoldAction (pub i32);],
 
   // generally card effects
   [ArmamentsAction {pub upgraded: bool}],
+  [HandOfGreedAction {pub target: usize, pub info: DamageInfo, pub gold: i32}],
+  [CalculatedGambleAction;],
+  [ApotheosisAction;],
 
   // generally monster effects
   [InitializeMonsterInnateDamageAmount{pub monster_index: usize, pub range: (i32, i32)}],
@@ -92,13 +116,28 @@ impl Action for PlayCard {
     let card_index = state.hand.iter().position(|c| *c == self.card).unwrap();
     let card = state.hand.remove(card_index);
     let card_id = card.card_info.id;
-    state.player.energy -= card.cost;
+    let cost = state.effective_cost(&card);
+    // X-cost cards use up all energy, even if that's 0
+    let x_value = state.player.energy;
+    state.player.energy -= if cost == X_COST { x_value } else { cost };
+    let card_type = card.card_info.card_type;
     state.card_in_play = Some(card);
 
     card_id.behavior(&mut PlayCardContext {
       runner,
       target: self.target,
+      x_value,
     });
+    // The repeat is free and doesn't count as playing another card. It happens while the card
+    // is still in play, so a card that exhausts is only exhausted once, after both plays.
+    if runner.state_mut().take_repeat_next(card_type) {
+      card_id.behavior(&mut PlayCardContext {
+        runner,
+        target: self.target,
+        x_value,
+      });
+    }
+    power_hook!(runner, AllCreatures, on_after_use_card(&self.card.clone()));
 
     runner.action_now(&FinishPlayingCard);
   }
@@ -108,24 +147,57 @@ impl Action for FinishPlayingCard {
   fn execute(&self, runner: &mut Runner) {
     let state = runner.state_mut();
     let card = state.card_in_play.take().unwrap();
-    if card.card_info.card_type == CardType::Power {
-      // card disappears
-    } else if card.card_info.exhausts {
-      state.exhaust_pile.push(card);
-    } else {
-      state.discard_pile.push(card);
+    // Rebound is used up by any card but a power, even one that exhausts instead
+    let rebound = card.card_info.card_type != CardType::Power
+      && state.player.creature.has_power(PowerId::Rebound);
+    match state.destination_after_play(&card) {
+      CardDestination::Removed => {}
+      CardDestination::Exhaust => exhaust_card(runner, card),
+      CardDestination::DrawPileTop => state.put_on_top_of_draw_pile(SingleCard {
+        cost_for_turn: None,
+        ..card
+      }),
+      CardDestination::Discard => state.discard_pile.push(SingleCard {
+        cost_for_turn: None,
+        ..card
+      }),
+    }
+    if rebound {
+      runner.action_now(&ReducePowerAction {
+        target: CreatureIndex::Player,
+        power_id: PowerId::Rebound,
+        amount: 1,
+      });
     }
   }
 }
 
+/// Moves a card to the exhaust pile and fires the `on_exhaust` hooks of the player's powers.
+///
+/// The listeners fire in the order of the player's powers (sorted by `priority()`, then by when they were applied),
+/// so with Feel No Pain and Dark Embrace, whichever was played first queues its effect first.
+pub fn exhaust_card(runner: &mut Runner, card: SingleCard) {
+  runner.state_mut().exhaust_pile.push(card.clone());
+  power_hook!(runner, CreatureIndex::Player, on_exhaust(&card));
+}
+
+// The whole round follows from this: the player's end of turn effects and hand cleanup (ethereal cards
+// exhaust), then `StartMonsterTurn`, `DoMonsterIntent` and `FinishMonsterTurn`, each going through the
+// monsters in order, and finally the end of round powers and the start of the player's next turn (block reset, energy, draw 5).
 impl Action for EndTurn {
   fn execute(&self, runner: &mut Runner) {
     power_hook!(runner, CreatureIndex::Player, at_end_of_turn());
+    trigger_orb_passives(runner);
+    tick_delayed_actions(runner);
 
     let state = runner.state_mut();
     state.turn_has_ended = true;
+    state.repeat_next.clear();
     let mut actions: ArrayVec<[DamageAction; 10]> = ArrayVec::new();
-    for card in state.hand.drain(..) {
+    let mut exhausted: ArrayVec<[SingleCard; 10]> = ArrayVec::new();
+    let mut retained: ArrayVec<[SingleCard; 10]> = ArrayVec::new();
+    for mut card in state.hand.drain(..) {
+      card.cost_for_turn = None;
       if card.card_info.id == CardId::Burn {
         actions.push(DamageAction {
           target: CreatureIndex::Player,
@@ -136,12 +208,19 @@ impl Action for EndTurn {
           ),
         });
       }
-      if card.card_info.ethereal {
-        state.exhaust_pile.push(card);
+      if card.card_info.retain {
+        retained.push(card);
+      } else if card.card_info.ethereal {
+        exhausted.push(card);
       } else {
         state.discard_pile.push(card);
       }
     }
+    // these were all in the hand a moment ago, so they can't go over the hand limit
+    state.hand.extend(retained);
+    for card in exhausted {
+      exhaust_card(runner, card);
+    }
     for action in actions {
       runner.action_bottom(action);
     }
@@ -150,42 +229,112 @@ impl Action for EndTurn {
   }
 }
 
+/// Counts down the delayed actions by one player turn, queueing the ones that are due.
+/// Each delayed action counts down separately, so e.g. two Bombs played on different turns go off on different turns.
+pub fn tick_delayed_actions(runner: &mut Runner) {
+  let state = runner.state_mut();
+  for delayed in &mut state.delayed_actions {
+    delayed.turns_left -= 1;
+  }
+  let (due, waiting): (Vec<DelayedAction>, Vec<DelayedAction>) = state
+    .delayed_actions
+    .drain(..)
+    .partition(|delayed| delayed.turns_left <= 0);
+  state.delayed_actions = waiting;
+  for delayed in due {
+    runner.action_bottom(delayed.action);
+  }
+}
+
 pub fn apply_end_of_turn_powers(runner: &mut Runner) {
   power_hook!(runner, AllMonsters, at_end_of_turn());
   power_hook!(runner, AllCreatures, at_end_of_round());
+  tick_down_powers(runner);
+}
+
+/// Takes 1 off every power that `ticks_down()`, removing it at 0. As in the game, a power that was
+/// `just_applied` during the monsters' turn is skipped once, so it still lasts through the player's next turn.
+pub fn tick_down_powers(runner: &mut Runner) {
+  let creatures: Vec<CreatureIndex> = std::iter::once(CreatureIndex::Player)
+    .chain(
+      (0..runner.state().monsters.len())
+        .filter(|&index| !runner.state().monsters[index].gone)
+        .map(CreatureIndex::Monster),
+    )
+    .collect();
+  for creature_index in creatures {
+    let mut ticking = Vec::new();
+    for power in &mut runner.state_mut().get_creature_mut(creature_index).powers {
+      if power.power_id.ticks_down() {
+        if power.just_applied {
+          power.just_applied = false;
+        } else {
+          ticking.push(power.power_id);
+        }
+      }
+    }
+    for power_id in ticking {
+      runner.action_now(&ReducePowerAction {
+        target: creature_index,
+        power_id,
+        amount: 1,
+      });
+    }
+  }
 }
+/// The start of a creature's turn happens in this order:
+/// 1. `at_start_of_turn` hooks, in the order of the creature's powers (sorted by `priority()`)
+/// 2. block is cleared, except what `modify_block_retention` keeps (Barricade, Calipers), and the player leaves Divinity
+/// 3. for the player only: energy is recharged (`on_energy_recharge` hooks), Plasma orbs give energy,
+///    then 5 cards are drawn
+///    (adjusted by `next_turn_draw_modifier`)
+/// 4. `at_start_of_turn_post_draw` hooks (queued behind the draw, so they see the new hand)
 pub fn start_creature_turn(runner: &mut Runner, creature_index: CreatureIndex) {
   power_hook!(runner, creature_index, at_start_of_turn());
-  let creature = runner.state_mut().get_creature_mut (creature_index);
-  if ! creature.has_power (PowerId::Barricade) {creature.block = 0;}
-  // TODO: make this actually post-draw
-  power_hook!(runner, creature_index, at_start_of_turn_post_draw());
+  let block = runner.state().get_creature(creature_index).block;
+  let mut retained = 0;
+  power_hook!(
+    runner.state(),
+    creature_index,
+    retained = modify_block_retention(block, retained)
+  );
+  runner.state_mut().get_creature_mut(creature_index).block = retained;
+  if creature_index == CreatureIndex::Player && runner.state().player.stance == Stance::Divinity {
+    runner.action_now(&ChangeStance(Stance::Neutral));
+  }
+  if creature_index != CreatureIndex::Player {
+    power_hook!(runner, creature_index, at_start_of_turn_post_draw());
+  }
 }
 
 impl Action for StartMonsterTurn {
   fn execute(&self, runner: &mut Runner) {
     if let Some(monster) = runner.state_mut().monsters.get_mut(self.0) {
       if !monster.gone {
-        start_creature_turn (runner, CreatureIndex::Monster (self.0));
+        start_creature_turn(runner, CreatureIndex::Monster(self.0));
       }
       if !runner.state().combat_over() {
         runner.action_now(&StartMonsterTurn(self.0 + 1));
       }
     } else {
-      runner.action_now(&DoMonsterIntent(0));
+      let acting_monsters = runner.state().monsters.len();
+      runner.action_now(&DoMonsterIntent(0, acting_monsters));
     }
   }
 }
 
+// The second field is how many monsters existed when the monster turn started. Monsters act in
+// index order, and ones spawned during the turn (e.g. by a slime splitting) are appended after
+// that count, so they wait until the next turn to act, like in the game.
 impl Action for DoMonsterIntent {
   fn execute(&self, runner: &mut Runner) {
-    if let Some(monster) = runner.state().monsters.get(self.0) {
+    if let Some(monster) = runner.state().monsters[..self.1].get(self.0) {
       let monster_id = monster.monster_id;
       if !monster.gone {
         monster_id.intent_effects(&mut DoIntentContext::new(runner, self.0));
       }
       if !runner.state().combat_over() {
-        runner.action_now(&DoMonsterIntent(self.0 + 1));
+        runner.action_now(&DoMonsterIntent(self.0 + 1, self.1));
       }
     } else {
       runner.action_bottom(FinishMonsterTurn(0));
@@ -207,15 +356,42 @@ impl Action for FinishMonsterTurn {
       let state = runner.state_mut();
       state.turn_number += 1;
       state.turn_has_ended = false;
-      start_creature_turn (runner, CreatureIndex::Player);
+      start_creature_turn(runner, CreatureIndex::Player);
+      let state = runner.state_mut();
+      state.player.energy = 3
+        + state
+          .player
+          .creature
+          .powers
+          .iter()
+          .map(|power| power.power_id.inherent_energy())
+          .sum::<i32>();
+      power_hook!(runner, CreatureIndex::Player, on_energy_recharge());
+      let plasma = runner
+        .state()
+        .player
+        .orbs
+        .iter()
+        .filter(|orb| orb.orb_type == OrbType::Plasma)
+        .count() as i32;
+      if plasma > 0 {
+        runner.action_now(&GainEnergyAction(plasma));
+      }
       let state = runner.state_mut();
-      state.player.energy = 3 + state.player.creature.powers.iter().map (| power | power.power_id.inherent_energy()).sum::<i32>();
-      runner.action_now(&DrawCards(5));
-      
+      let draw = 5 + state.next_turn_draw_modifier;
+      state.next_turn_draw_modifier = 0;
+      runner.action_now(&DrawCards(draw));
+      runner.action_now(&StartOfTurnPostDraw(CreatureIndex::Player));
     }
   }
 }
 
+impl Action for StartOfTurnPostDraw {
+  fn execute(&self, runner: &mut Runner) {
+    power_hook!(runner, self.0, at_start_of_turn_post_draw());
+  }
+}
+
 impl Action for ChooseMonsterIntent {
   fn determinism(&self, state: &CombatState) -> Determinism {
     Determinism::Random(monsters::intent_choice_distribution(state, self.0))
@@ -232,33 +408,54 @@ impl Action for ChooseMonsterIntent {
 
 impl Action for DamageAction {
   fn execute(&self, runner: &mut Runner) {
+    // like the game, damage from a monster that died before it resolved (e.g. to Poison) is cancelled
+    if let CreatureIndex::Monster(owner) = self.info.owner {
+      if runner.state().monsters[owner].gone {
+        return;
+      }
+    }
     let mut damage = self.info.output;
-    //TODO: intangible
     if damage < 0 {
       damage = 0;
     }
-
-    let target = runner.state_mut().get_creature_mut(self.target);
-    if damage >= target.block {
-      damage -= target.block;
-      target.block = 0;
-    } else {
-      target.block -= damage;
-      damage = 0;
+    // unlike the damage hooks, this also caps HP loss such as Poison
+    let target = runner.state().get_creature(self.target);
+    if damage > 1 && (target.has_power(PowerId::Intangible) || target.has_power(PowerId::IntangiblePlayer)) {
+      damage = 1;
     }
 
+    damage = runner
+      .state_mut()
+      .get_creature_mut(self.target)
+      .block_damage(damage, self.info.damage_type);
+
     // TODO: various relic hooks
+    power_hook!(
+      runner.state(),
+      self.info.owner,
+      damage = on_attack_to_change_damage(&self.info, damage)
+    );
     power_hook!(
       runner.state(),
       self.target,
       damage = on_attacked_to_change_damage(damage)
     );
+    // Buffer only gets used up by damage that gets through block. Like in the game, this includes HP loss,
+    // even the player's own from cards like Hemokinesis.
+    if damage > 0 && runner.state().get_creature(self.target).has_power(PowerId::Buffer) {
+      runner.action_now(&ReducePowerAction {
+        target: self.target,
+        power_id: PowerId::Buffer,
+        amount: 1,
+      });
+      damage = 0;
+    }
+    power_hook!(runner, self.info.owner, on_attack(self.info.clone(), damage, self.target));
     power_hook!(runner, self.target, on_attacked(self.info.clone(), damage));
 
     let target = runner.state_mut().get_creature_mut(self.target);
-    target.hitpoints -= damage;
+    target.lose_hitpoints(damage);
     if target.hitpoints <= 0 {
-      target.hitpoints = 0;
       match self.target {
         CreatureIndex::Player => {}
         CreatureIndex::Monster(monster_index) => {
@@ -266,6 +463,7 @@ impl Action for DamageAction {
           power_hook!(runner, self.target, on_death());
         }
       }
+      runner.state_mut().update_combat_over();
     }
   }
 }
@@ -299,25 +497,55 @@ impl Action for AttackDamageRandomEnemyAction {
     // hack: this is not quite where powers are applied to card/monster damage in the actual code
     let target = CreatureIndex::Monster(random_value as usize);
     let mut info = DamageInfo::new(CreatureIndex::Player, self.damage, DamageType::Normal);
-    info.apply_powers(runner.state(), CreatureIndex::Player, target);
+    // the card that queued this has already left play, so card-specific bonuses don't apply
+    info.apply_powers(runner.state(), CreatureIndex::Player, target, None);
     runner.action_now(&DamageAction { target, info });
   }
 }
 
+impl Action for DamageRandomEnemyAction {
+  fn determinism(&self, state: &CombatState) -> Determinism {
+    AttackDamageRandomEnemyAction { damage: self.damage }.determinism(state)
+  }
+  fn execute_random(&self, runner: &mut Runner, random_value: i32) {
+    runner.action_now(&DamageAction {
+      target: CreatureIndex::Monster(random_value as usize),
+      info: DamageInfo::new(CreatureIndex::Player, self.damage, self.damage_type),
+    });
+  }
+}
+
 impl Action for DrawCardRandom {
   fn determinism(&self, state: &CombatState) -> Determinism {
+    if state.draw_pile_known_top > 0 {
+      return Determinism::Deterministic;
+    }
     Determinism::Random(Distribution(
       (0..state.draw_pile.len() as i32)
         .map(|index| (1.0, index))
         .collect(),
     ))
   }
+  fn execute(&self, runner: &mut Runner) {
+    let state = runner.state_mut();
+    let card = state.remove_from_draw_pile(state.draw_pile.len() - 1);
+    draw_card(runner, card);
+  }
   fn execute_random(&self, runner: &mut Runner, random_value: i32) {
-    let card = runner.state_mut().draw_pile.remove(random_value as usize);
-    runner.state_mut().hand.push(card);
+    let card = runner.state_mut().remove_from_draw_pile(random_value as usize);
+    draw_card(runner, card);
+  }
+}
+
+fn draw_card(runner: &mut Runner, card: SingleCard) {
+  if runner.state_mut().add_card_to_hand(card.clone()) {
+    power_hook!(runner, CreatureIndex::Player, on_card_draw(&card));
   }
 }
 
+// Draws one card at a time, so each `DrawCardRandom` is its own random outcome (or a deterministic
+// one, for cards known to be on top). If the draw pile runs out, the discard pile becomes the draw
+// pile. As in the game, drawing with a full hand just stops, leaving the rest in the draw pile.
 impl Action for DrawCards {
   fn execute(&self, runner: &mut Runner) {
     if runner.state().player.creature.has_power (PowerId::NoDraw) {
@@ -334,14 +562,44 @@ impl Action for DrawCards {
     }
     if state.draw_pile.is_empty() {
       std::mem::swap(&mut state.draw_pile, &mut state.discard_pile);
+      runner.shuffle_draw_pile();
     }
-    if !state.draw_pile.is_empty() {
+    if !runner.state().draw_pile.is_empty() {
       runner.action_now(&DrawCardRandom);
       runner.action_now(&DrawCards(self.0 - 1));
     }
   }
 }
 
+// the whole draw pile gets shuffled, not just the cards from the discard pile
+impl Action for ShuffleDiscardIntoDrawPile {
+  fn execute(&self, runner: &mut Runner) {
+    let state = runner.state_mut();
+    state.draw_pile.append(&mut state.discard_pile);
+    runner.shuffle_draw_pile();
+  }
+}
+
+impl Action for DrawToHandSize {
+  fn execute(&self, runner: &mut Runner) {
+    let missing = self.0 - runner.state().hand.len() as i32;
+    if missing > 0 {
+      runner.action_now(&DrawCards(missing));
+    }
+  }
+}
+
+impl ApplyPowerAction {
+  /// Whether this counts as a debuff (for Artifact and the like). As in the game, this includes
+  /// negative Strength, Dexterity or Focus, like the Lagavulin's Siphon Soul.
+  pub fn is_debuff(&self) -> bool {
+    match self.power_id {
+      PowerId::Strength | PowerId::Dexterity | PowerId::Focus => self.amount < 0,
+      power_id => power_id.power_type() == PowerType::Debuff,
+    }
+  }
+}
+
 impl Action for ApplyPowerAction {
   fn execute(&self, runner: &mut Runner) {
     if let CreatureIndex::Monster(monster_index) = self.target {
@@ -356,7 +614,7 @@ impl Action for ApplyPowerAction {
       .state()
       .get_creature(self.target)
       .has_power(PowerId::Artifact)
-      && self.power_id.power_type() == PowerType::Debuff
+      && self.is_debuff()
     {
       power_hook!(
         runner,
@@ -367,6 +625,9 @@ impl Action for ApplyPowerAction {
       return;
     }
 
+    // A power applied during the monsters' turn skips its first tick down (see `tick_down_powers`),
+    // so e.g. Weak from a monster lasts through the player's next turn. Stacking onto a power that's
+    // already there doesn't reset this, as in the game.
     let just_applied = runner.state().turn_has_ended;
 
     //if this.source == CreatureIndex::Player && this.target != this.source && {
@@ -388,6 +649,15 @@ impl Action for ApplyPowerAction {
       });
       target.powers.sort_by_key(|power| power.power_id.priority());
     }
+
+    // debuffs negated by Artifact returned early, so they never reach this
+    if self.is_debuff() && self.source != self.target {
+      power_hook!(
+        runner,
+        self.source,
+        on_debuff_applied(self.target, self.power_id)
+      );
+    }
   }
 }
 
@@ -428,6 +698,7 @@ impl Action for GainBlockAction {
     let creature = runner.state_mut().get_creature_mut(self.creature_index);
     if self.amount > 0 {
       creature.block += self.amount;
+      power_hook!(runner, self.creature_index, on_gained_block(self.amount as f64));
     }
   }
 }
@@ -438,12 +709,417 @@ impl Action for GainEnergyAction {
   }
 }
 
+// Channeling with every slot full evokes the oldest orb to make room.
+impl Action for ChannelOrb {
+  fn execute(&self, runner: &mut Runner) {
+    let player = &runner.state().player;
+    if player.orb_slots <= 0 {
+      return;
+    }
+    if player.orbs.len() as i32 >= player.orb_slots {
+      runner.action_now(&EvokeOrb);
+    }
+    runner.state_mut().player.orbs.push(Orb::new(self.0));
+  }
+}
+
+impl Action for EvokeOrb {
+  fn execute(&self, runner: &mut Runner) {
+    if runner.state().player.orbs.is_empty() {
+      return;
+    }
+    let orb = runner.state_mut().player.orbs.remove(0);
+    let focus = runner.state().player.creature.power_amount(PowerId::Focus);
+    match orb.orb_type {
+      OrbType::Lightning => runner.action_now(&DamageRandomEnemyAction {
+        damage: std::cmp::max(0, 8 + focus),
+        damage_type: DamageType::Thorns,
+      }),
+      OrbType::Frost => runner.action_now(&GainBlockAction {
+        creature_index: CreatureIndex::Player,
+        amount: std::cmp::max(0, 5 + focus),
+      }),
+      OrbType::Dark => {
+        let weakest = runner
+          .state()
+          .monsters
+          .iter()
+          .enumerate()
+          .filter(|(_, monster)| !monster.gone)
+          .min_by_key(|(_, monster)| monster.creature.hitpoints)
+          .map(|(index, _)| index);
+        if let Some(index) = weakest {
+          runner.action_now(&DamageAction {
+            target: CreatureIndex::Monster(index),
+            info: DamageInfo::new(CreatureIndex::Player, orb.misc, DamageType::Thorns),
+          });
+        }
+      }
+      OrbType::Plasma => runner.action_now(&GainEnergyAction(2)),
+    }
+  }
+}
+
+// Darkness+: every Dark orb, including one just channeled, grows as if its passive had triggered.
+impl Action for DarkImpulse {
+  fn execute(&self, runner: &mut Runner) {
+    let focus = runner.state().player.creature.power_amount(PowerId::Focus);
+    for orb in &mut runner.state_mut().player.orbs {
+      if orb.orb_type == OrbType::Dark {
+        orb.misc += std::cmp::max(0, 6 + focus);
+      }
+    }
+  }
+}
+
+/// Triggers the end-of-turn passives of all orbs, oldest first. Plasma's passive happens at the
+/// start of the turn instead (see `FinishMonsterTurn`).
+pub fn trigger_orb_passives(runner: &mut Runner) {
+  let focus = runner.state().player.creature.power_amount(PowerId::Focus);
+  for index in 0..runner.state().player.orbs.len() {
+    match runner.state().player.orbs[index].orb_type {
+      OrbType::Lightning => runner.action_bottom(DamageRandomEnemyAction {
+        damage: std::cmp::max(0, 3 + focus),
+        damage_type: DamageType::Thorns,
+      }),
+      OrbType::Frost => runner.action_bottom(GainBlockAction {
+        creature_index: CreatureIndex::Player,
+        amount: std::cmp::max(0, 2 + focus),
+      }),
+      OrbType::Dark => runner.state_mut().player.orbs[index].misc += std::cmp::max(0, 6 + focus),
+      OrbType::Plasma => {}
+    }
+  }
+}
+
+// Entering the stance you're already in does nothing, so it doesn't set off any exit effects.
+impl Action for ChangeStance {
+  fn execute(&self, runner: &mut Runner) {
+    let old_stance = runner.state().player.stance;
+    if old_stance == self.0 {
+      return;
+    }
+    runner.state_mut().player.stance = self.0;
+    if old_stance == Stance::Calm {
+      runner.action_now(&GainEnergyAction(2));
+    }
+    if self.0 == Stance::Divinity {
+      runner.action_now(&GainEnergyAction(3));
+    }
+
+    for index in 0..runner.state().discard_pile.len() {
+      let card = runner.state().discard_pile[index].clone();
+      card.card_info.id.on_stance_change(runner, &card);
+    }
+  }
+}
+
+impl Action for DiscardToHand {
+  fn execute(&self, runner: &mut Runner) {
+    let state = runner.state_mut();
+    if let Some(index) = state.discard_pile.iter().position(|card| *card == self.0) {
+      // with a full hand, this puts it right back
+      let card = state.discard_pile.remove(index);
+      state.add_card_to_hand(card);
+    }
+  }
+}
+
+impl Action for DelayAction {
+  fn execute(&self, runner: &mut Runner) {
+    runner.state_mut().delayed_actions.push(DelayedAction {
+      turns_left: self.turns,
+      action: (*self.action).clone(),
+    });
+  }
+}
+
+impl Action for RepeatNextCardsAction {
+  fn execute(&self, runner: &mut Runner) {
+    runner
+      .state_mut()
+      .add_repeat_next(self.card_type, self.count);
+  }
+}
+
+impl Action for GainGoldAction {
+  fn execute(&self, runner: &mut Runner) {
+    runner.state_mut().gold_gained += self.0;
+  }
+}
+
+impl Action for LoseGoldAction {
+  fn execute(&self, runner: &mut Runner) {
+    let state = runner.state_mut();
+    state.gold_gained -= std::cmp::min(self.0, state.gold());
+  }
+}
+
 impl Action for DiscardNewCard {
   fn execute(&self, runner: &mut Runner) {
     runner.state_mut().discard_pile.push(self.0.clone());
   }
 }
 
+// For effects that shuffle a card into the draw pile: it can land anywhere, including among the known top cards.
+impl Action for DrawPileNewCard {
+  fn determinism(&self, state: &CombatState) -> Determinism {
+    self.as_add_card_to_pile().determinism(state)
+  }
+  fn execute(&self, runner: &mut Runner) {
+    self.as_add_card_to_pile().execute(runner);
+  }
+  fn execute_random(&self, runner: &mut Runner, random_value: i32) {
+    self.as_add_card_to_pile().execute_random(runner, random_value);
+  }
+}
+
+impl DrawPileNewCard {
+  fn as_add_card_to_pile(&self) -> AddCardToPile {
+    AddCardToPile {
+      card: self.0.clone(),
+      pile: PileLocation::Draw,
+      position: CardPosition::Random,
+    }
+  }
+}
+
+impl Action for HandNewCard {
+  fn execute(&self, runner: &mut Runner) {
+    runner.state_mut().add_card_to_hand(self.0.clone());
+  }
+}
+
+impl Action for AddCardToPile {
+  fn determinism(&self, state: &CombatState) -> Determinism {
+    if self.pile == PileLocation::Draw && self.position == CardPosition::Random {
+      // The random value is how many of the known top cards end up above the new card. Landing anywhere
+      // below them is one outcome, since the rest of the draw pile is drawn in random order anyway,
+      // so with no known cards on top, there's nothing random about it.
+      let known_top = state.draw_pile_known_top;
+      if known_top == 0 {
+        return Determinism::Deterministic;
+      }
+      let unknown = state.draw_pile.len() - known_top;
+      let mut distribution = Distribution::new();
+      for above in 0..known_top {
+        distribution.0.push((1.0, above as i32));
+      }
+      distribution.0.push(((unknown + 1) as f64, known_top as i32));
+      Determinism::Random(distribution)
+    } else {
+      Determinism::Deterministic
+    }
+  }
+  fn execute(&self, runner: &mut Runner) {
+    let state = runner.state_mut();
+    let card = self.card.clone();
+    match self.pile {
+      PileLocation::Hand => {
+        state.add_card_to_hand(card);
+      }
+      PileLocation::Discard => state.discard_pile.push(card),
+      PileLocation::Exhaust => state.exhaust_pile.push(card),
+      PileLocation::Draw => match self.position {
+        CardPosition::Top => state.put_on_top_of_draw_pile(card),
+        CardPosition::Bottom => {
+          state.draw_pile.insert(0, card);
+          // we can only keep track of the bottom card if we know the order of the whole pile
+          if state.draw_pile_known_top + 1 == state.draw_pile.len() {
+            state.draw_pile_known_top += 1;
+          }
+        }
+        CardPosition::Random => state.add_card_to_draw_pile(card),
+      },
+    }
+  }
+  fn execute_random(&self, runner: &mut Runner, random_value: i32) {
+    let state = runner.state_mut();
+    let above = random_value as usize;
+    if above == state.draw_pile_known_top {
+      state.add_card_to_draw_pile(self.card.clone());
+    } else {
+      let index = state.draw_pile.len() - above;
+      state.draw_pile.insert(index, self.card.clone());
+      state.draw_pile_known_top += 1;
+    }
+  }
+}
+
+impl Action for HandRandomCardAction {
+  fn determinism(&self, state: &CombatState) -> Determinism {
+    Determinism::Random(Distribution(
+      (0..cards::combat_card_pool(self.class, self.card_type).count() as i32)
+        .map(|index| (1.0, index))
+        .collect(),
+    ))
+  }
+  fn execute_random(&self, runner: &mut Runner, random_value: i32) {
+    let id = cards::combat_card_pool(self.class, self.card_type)
+      .nth(random_value as usize)
+      .unwrap();
+    let mut card = SingleCard::create(id);
+    card.cost_for_turn = self.cost_for_turn;
+    runner.action_now(&HandNewCard(card));
+  }
+}
+
+impl Action for ExhaustRandomCardAction {
+  fn determinism(&self, state: &CombatState) -> Determinism {
+    if state.hand.is_empty() {
+      Determinism::Deterministic
+    } else {
+      Determinism::Random(Distribution(
+        (0..state.hand.len() as i32)
+          .map(|index| (1.0, index))
+          .collect(),
+      ))
+    }
+  }
+  fn execute(&self, runner: &mut Runner) {}
+  fn execute_random(&self, runner: &mut Runner, random_value: i32) {
+    let card = runner.state_mut().hand.remove(random_value as usize);
+    exhaust_card(runner, card);
+  }
+}
+
+impl ChooseCardsAction {
+  pub fn new(choice_type: CardChoiceType, count: i32) -> ChooseCardsAction {
+    ChooseCardsAction {
+      choice_type,
+      count,
+      any_number: false,
+    }
+  }
+}
+
+// With `any_number`, the player may pick up to `count` cards and use `StopChoosingCards` to pick
+// fewer; otherwise they must pick exactly `count` (or every candidate, if there aren't that many).
+impl Action for ChooseCardsAction {
+  fn determinism(&self, state: &CombatState) -> Determinism {
+    let candidates = self.choice_type.candidates(state).count();
+    if candidates == 0 || (!self.any_number && candidates as i32 <= self.count) {
+      Determinism::Deterministic
+    } else {
+      Determinism::Choice
+    }
+  }
+  fn execute(&self, runner: &mut Runner) {
+    let candidates: Vec<usize> = self.choice_type.candidates(runner.state()).collect();
+    // in reverse, so that taking a card out of the pile doesn't shift the ones still to come
+    for card_index in candidates.into_iter().rev() {
+      resolve_card_choice(runner, self.choice_type, card_index);
+    }
+  }
+}
+
+impl Action for ChooseCard {
+  fn execute(&self, runner: &mut Runner) {
+    let state = runner.state_mut();
+    let pile = self.choice_type.pile(state);
+    assert!(
+      self.card_index < pile.len() && self.choice_type.allows(&pile[self.card_index]),
+      "{:?} doesn't name a card that can be chosen",
+      self
+    );
+    match state.stale_subaction_stack.pop() {
+      Some(DynAction::ChooseCardsAction(pending)) if pending.choice_type == self.choice_type => {
+        if pending.count > 1 {
+          state.stale_subaction_stack.push(
+            ChooseCardsAction {
+              count: pending.count - 1,
+              ..pending
+            }
+            .into(),
+          );
+        }
+      }
+      other => panic!("made {:?} while {:?} was pending", self, other),
+    }
+    resolve_card_choice(runner, self.choice_type, self.card_index);
+  }
+}
+
+impl Action for StopChoosingCards {
+  fn execute(&self, runner: &mut Runner) {
+    match runner.state_mut().stale_subaction_stack.pop() {
+      Some(DynAction::ChooseCardsAction(pending)) if pending.any_number => {}
+      other => panic!("stopped choosing cards while {:?} was pending", other),
+    }
+  }
+}
+
+pub fn resolve_card_choice(runner: &mut Runner, choice_type: CardChoiceType, card_index: usize) {
+  let state = runner.state_mut();
+  match choice_type {
+    CardChoiceType::DiscardCard => {
+      let card = state.hand.remove(card_index);
+      state.discard_pile.push(SingleCard {
+        cost_for_turn: None,
+        ..card
+      });
+    }
+    CardChoiceType::ExhaustCard => {
+      let card = state.hand.remove(card_index);
+      exhaust_card(runner, card);
+    }
+    CardChoiceType::HandTopdeck => {
+      let card = state.hand.remove(card_index);
+      state.put_on_top_of_draw_pile(SingleCard {
+        cost_for_turn: None,
+        ..card
+      });
+    }
+    CardChoiceType::SetupTopdeck => {
+      let card = state.hand.remove(card_index);
+      // `cost_for_turn` survives the draw pile, so this lasts until the card is played or the turn it's drawn ends
+      let cost_for_turn = if card.cost > 0 { Some(0) } else { None };
+      state.put_on_top_of_draw_pile(SingleCard {
+        cost_for_turn,
+        ..card
+      });
+    }
+    CardChoiceType::DiscardTopdeck => {
+      let card = state.discard_pile.remove(card_index);
+      state.put_on_top_of_draw_pile(card);
+    }
+    CardChoiceType::TutorSkill | CardChoiceType::TutorAttack => {
+      let card = state.remove_from_draw_pile(card_index);
+      runner.action_now(&HandNewCard(card));
+    }
+    CardChoiceType::Duplicate(copies) => {
+      let card = state.hand[card_index].clone();
+      for _ in 0..copies {
+        runner.action_now(&HandNewCard(card.clone()));
+      }
+    }
+  }
+}
+
+// the Calculated Gamble itself is in play rather than in the hand, so it isn't counted
+impl Action for CalculatedGambleAction {
+  fn execute(&self, runner: &mut Runner) {
+    let state = runner.state_mut();
+    let count = state.hand.len() as i32;
+    for card in state.hand.drain(..) {
+      state.discard_pile.push(SingleCard {
+        cost_for_turn: None,
+        ..card
+      });
+    }
+    runner.action_now(&DrawCards(count));
+  }
+}
+
+// by the time this runs, the Apotheosis itself is already in the exhaust pile, so it gets upgraded too,
+// which makes no difference
+impl Action for ApotheosisAction {
+  fn execute(&self, runner: &mut Runner) {
+    for card in runner.state_mut().all_cards_mut() {
+      card.upgrade();
+    }
+  }
+}
 
 impl Action for ArmamentsAction {
   fn determinism(&self, state: & CombatState)->Determinism {
@@ -472,6 +1148,20 @@ impl Action for ArmamentsAction {
 }
 
 
+impl Action for HandOfGreedAction {
+  fn execute(&self, runner: &mut Runner) {
+    let was_alive = !runner.state().monsters[self.target].gone;
+    runner.action_now(&DamageAction {
+      target: CreatureIndex::Monster(self.target),
+      info: self.info.clone(),
+    });
+    if was_alive && runner.state().monsters[self.target].gone {
+      runner.action_now(&GainGoldAction(self.gold));
+    }
+  }
+}
+
+
 impl Action for InitializeMonsterInnateDamageAmount {
   fn determinism(&self, state: &CombatState) -> Determinism {
     Determinism::Random(Distribution(
@@ -521,6 +1211,7 @@ impl Action for SplitAction {
       ascension: splitting.ascension,
       move_history: Vec::new(),
       gone: false,
+      intent_cache: None,
       creature: Creature {
         hitpoints: splitting.creature.hitpoints,
         max_hitpoints: splitting.creature.hitpoints,
@@ -531,10 +1222,9 @@ impl Action for SplitAction {
 
     splitting.creature.hitpoints = 0;
     splitting.gone = true;
-    runner
-      .state_mut()
-      .monsters
-      .extend(new_monsters.iter().cloned());
+    let state = runner.state_mut();
+    state.monsters.extend(new_monsters.iter().cloned());
+    state.update_combat_over();
   }
 }
 
@@ -544,5 +1234,410 @@ impl Action for EscapeAction {
     let escaping = &mut state.monsters[self.0];
 
     escaping.gone = true;
+    state.update_combat_over();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::simulation_state::testing::*;
+
+  fn strikes(count: usize) -> Vec<SingleCard> {
+    (0..count).map(|_| card(CardId::StrikeR)).collect()
+  }
+
+  fn cultist_combat(hand: Vec<SingleCard>) -> CombatState {
+    combat(vec![monster(MonsterId::Cultist, 50, vec![1])], hand)
+  }
+
+  #[test]
+  fn ending_the_turn_resets_energy_and_exhausts_ethereal_cards() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![3])],
+      vec![card(CardId::StrikeR), card(CardId::Dazed)],
+    );
+    play(&mut state, CardId::StrikeR, 0);
+    assert_eq!(state.player.energy, 2);
+
+    end_turn(&mut state);
+    assert_eq!(state.player.energy, 3);
+    assert_eq!(state.exhaust_pile, vec![card(CardId::Dazed)]);
+    assert_eq!(state.hand.as_slice(), &[card(CardId::StrikeR)]);
+  }
+
+  #[test]
+  fn weak_from_a_monster_lasts_through_the_end_of_its_turn() {
+    let mut state = combat(vec![monster(MonsterId::GreenLouse, 15, vec![4])], Vec::new());
+    end_turn(&mut state);
+    let weak = state.player.creature.powers.iter().find(|power| power.power_id == PowerId::Weak).unwrap();
+    assert_eq!(weak.amount, 2);
+    assert!(!weak.just_applied);
+  }
+
+  #[test]
+  fn drawing_stops_at_a_full_hand() {
+    let mut state = cultist_combat(strikes(9));
+    state.draw_pile = vec![card(CardId::DefendR); 3];
+    run(&mut state, &DrawCards(3));
+    assert_eq!(state.hand.len(), 10);
+    assert_eq!(state.draw_pile.len(), 2);
+    assert!(state.discard_pile.is_empty());
+  }
+
+  #[test]
+  fn a_card_drawn_into_a_full_hand_is_discarded() {
+    let mut state = cultist_combat(strikes(10));
+    state.draw_pile = vec![card(CardId::DefendR)];
+    run(&mut state, &DrawCardRandom);
+    assert_eq!(state.hand.len(), 10);
+    assert_eq!(state.discard_pile, vec![card(CardId::DefendR)]);
+  }
+
+  #[test]
+  fn cards_added_to_a_full_hand_go_to_the_discard_pile() {
+    let mut state = cultist_combat(strikes(10));
+    state.discard_pile = vec![card(CardId::FlurryOfBlows)];
+    run(&mut state, &ChangeStance(Stance::Wrath));
+    run(&mut state, &HandNewCard(card(CardId::Shiv)));
+    assert_eq!(state.hand.len(), 10);
+    assert_eq!(state.discard_pile, vec![card(CardId::FlurryOfBlows), card(CardId::Shiv)]);
+    assert!(state.limbo.is_empty());
+  }
+
+  #[test]
+  fn the_discard_pile_is_only_reshuffled_once_the_draw_pile_is_empty() {
+    let mut state = cultist_combat(Vec::new());
+    state.draw_pile = vec![card(CardId::DefendR)];
+    state.discard_pile = strikes(3);
+    run(&mut state, &DrawCards(1));
+    assert_eq!(state.hand.as_slice(), &[card(CardId::DefendR)]);
+    assert_eq!(state.discard_pile.len(), 3);
+
+    run(&mut state, &DrawCards(2));
+    assert_eq!(state.hand.len(), 3);
+    assert_eq!(state.draw_pile.len(), 1);
+    assert!(state.discard_pile.is_empty());
+  }
+
+  #[test]
+  fn known_cards_on_top_are_drawn_deterministically() {
+    let mut state = cultist_combat(Vec::new());
+    state.draw_pile = vec![card(CardId::DefendR), card(CardId::Bash)];
+    state.draw_pile_known_top = 1;
+    assert_eq!(DrawCardRandom.determinism(&state), Determinism::Deterministic);
+    run(&mut state, &DrawCards(1));
+    assert_eq!(state.hand.as_slice(), &[card(CardId::Bash)]);
+
+    match DrawCardRandom.determinism(&state) {
+      Determinism::Random(distribution) => assert_eq!(distribution.0.len(), 1),
+      other => panic!("expected a random draw, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn shuffling_a_card_in_branches_over_the_known_positions() {
+    let mut state = cultist_combat(Vec::new());
+    let dazed = DrawPileNewCard(card(CardId::Dazed));
+    state.draw_pile = strikes(3);
+    assert_eq!(dazed.determinism(&state), Determinism::Deterministic);
+
+    // the top two are known: the Dazed can go above both, between them, or anywhere below them
+    state.draw_pile_known_top = 2;
+    let distribution = match dazed.determinism(&state) {
+      Determinism::Random(distribution) => distribution,
+      other => panic!("expected a random position, got {:?}", other),
+    };
+    assert_eq!(distribution.0.as_slice(), &[(1.0, 0), (1.0, 1), (2.0, 2)]);
+
+    let mut scratch = state.clone();
+    Runner::with_seed(&mut scratch, false, false, 0).apply_outcome(&dazed, 0);
+    assert_eq!(scratch.draw_pile.last(), Some(&card(CardId::Dazed)));
+    assert_eq!(scratch.draw_pile_known_top, 3);
+
+    let mut scratch = state.clone();
+    Runner::with_seed(&mut scratch, false, false, 0).apply_outcome(&dazed, 2);
+    assert_eq!(scratch.draw_pile[1], card(CardId::Dazed));
+    assert_eq!(scratch.draw_pile_known_top, 2);
+  }
+
+  #[test]
+  fn repulsor_shuffles_dazed_into_the_draw_pile() {
+    let mut state = combat(vec![monster(MonsterId::Repulsor, 30, vec![1])], Vec::new());
+    end_turn(&mut state);
+    let dazed = state.draw_pile.iter().chain(&state.hand).filter(|card| card.card_info.id == CardId::Dazed);
+    assert_eq!(dazed.count(), 2);
+  }
+
+  #[test]
+  fn channeling_into_full_slots_evokes_the_oldest_orb() {
+    let mut state = cultist_combat(Vec::new());
+    state.player.orb_slots = 2;
+    run(&mut state, &ChannelOrb(OrbType::Frost));
+    run(&mut state, &ChannelOrb(OrbType::Dark));
+    run(&mut state, &ChannelOrb(OrbType::Lightning));
+    let orbs: Vec<OrbType> = state.player.orbs.iter().map(|orb| orb.orb_type).collect();
+    assert_eq!(orbs, vec![OrbType::Dark, OrbType::Lightning]);
+    assert_eq!(state.player.creature.block, 5);
+  }
+
+  #[test]
+  fn lightning_hits_a_random_living_monster() {
+    let mut state = combat(
+      vec![
+        monster(MonsterId::Cultist, 50, vec![3]),
+        monster(MonsterId::Cultist, 50, vec![3]),
+        monster(MonsterId::Cultist, 50, vec![3]),
+      ],
+      Vec::new(),
+    );
+    state.monsters[1].gone = true;
+    let lightning = DamageRandomEnemyAction {
+      damage: 8,
+      damage_type: DamageType::Thorns,
+    };
+    match lightning.determinism(&state) {
+      Determinism::Random(distribution) => {
+        assert_eq!(distribution.0.as_slice(), &[(1.0, 0), (1.0, 2)])
+      }
+      other => panic!("expected a random target, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn juggernaut_triggers_once_per_block_gain() {
+    let mut state = cultist_combat(vec![card(CardId::DefendR), card(CardId::DefendR)]);
+    state.player.creature.powers.push(power(PowerId::Juggernaut, 5));
+    play(&mut state, CardId::DefendR, 0);
+    assert_eq!(state.monsters[0].creature.hitpoints, 45);
+    play(&mut state, CardId::DefendR, 0);
+    assert_eq!(state.monsters[0].creature.hitpoints, 40);
+    assert_eq!(state.player.creature.block, 10);
+
+    run(
+      &mut state,
+      &GainBlockAction {
+        creature_index: CreatureIndex::Player,
+        amount: 0,
+      },
+    );
+    assert_eq!(state.monsters[0].creature.hitpoints, 40);
+  }
+
+  #[test]
+  fn gold_can_not_go_below_0() {
+    let mut state = cultist_combat(Vec::new());
+    state.starting_gold = 20;
+    run(&mut state, &GainGoldAction(15));
+    assert_eq!(state.gold(), 35);
+    run(&mut state, &LoseGoldAction(50));
+    assert_eq!(state.gold(), 0);
+    assert_eq!(state.gold_gained, -20);
+  }
+
+  #[test]
+  fn combat_is_won_only_once_every_monster_has_fled() {
+    let mut state = combat(
+      vec![
+        monster(MonsterId::Looter, 44, vec![3]),
+        monster(MonsterId::Looter, 44, vec![1]),
+      ],
+      Vec::new(),
+    );
+    end_turn(&mut state);
+    assert!(state.monsters[0].gone);
+    assert_eq!(state.monsters[0].creature.hitpoints, 44);
+    assert!(!state.combat_over());
+
+    run(&mut state, &EscapeAction(1));
+    assert!(state.combat_over());
+    assert!(state.combat_won());
+    assert!(!state.combat_lost());
+  }
+
+  #[test]
+  fn monsters_act_in_order_and_ones_spawned_mid_turn_wait() {
+    let mut slime = monster(MonsterId::SpikeSlimeL, 64, vec![1]);
+    slime.creature.hitpoints = 30;
+    let mut state = combat(
+      vec![
+        slime,
+        monster(MonsterId::Cultist, 50, vec![1]),
+        monster(MonsterId::JawWorm, 40, vec![1]),
+      ],
+      Vec::new(),
+    );
+    let mut attackers = Vec::new();
+    let mut observer = |_: &CombatState, action: &DynAction| {
+      if let DynAction::DamageAction(DamageAction { info, target: CreatureIndex::Player }) = action {
+        attackers.push(info.owner);
+      }
+    };
+    let mut runner = Runner::with_seed(&mut state, true, false, 0).with_step_observer(&mut observer);
+    runner.action_now(&EndTurn);
+    run_until_unable(&mut runner);
+    drop(runner);
+
+    assert_eq!(attackers, vec![CreatureIndex::Monster(1), CreatureIndex::Monster(2)]);
+    assert_eq!(state.player.creature.hitpoints, 80 - 6 - 11);
+    assert!(state.monsters[0].gone);
+    assert_eq!(state.monsters.len(), 5);
+    assert!(state.monsters[3..].iter().all(|monster| monster.creature.hitpoints == 30));
+  }
+
+  #[test]
+  fn warcry_offers_each_card_in_hand_to_put_back() {
+    let mut state = cultist_combat(vec![card(CardId::Warcry), card(CardId::StrikeR), card(CardId::DefendR)]);
+    state.draw_pile = vec![card(CardId::Bash)];
+    play(&mut state, CardId::Warcry, 0);
+    assert_eq!(state.hand.len(), 3);
+    let choices: Vec<Choice> = (0..3)
+      .map(|card_index| {
+        Choice::from(ChooseCard {
+          choice_type: CardChoiceType::HandTopdeck,
+          card_index,
+        })
+      })
+      .collect();
+    assert_eq!(state.legal_choices(), choices);
+
+    run(&mut state, &choices[1]);
+    assert!(state.pending_choice().is_none());
+    assert_eq!(state.hand.as_slice(), &[card(CardId::StrikeR), card(CardId::Bash)]);
+    assert_eq!(state.draw_pile, vec![card(CardId::DefendR)]);
+    assert_eq!(state.draw_pile_known_top, 1);
+  }
+
+  #[test]
+  fn a_parasite_shuffled_into_the_draw_pile_is_drawn_like_any_card() {
+    let mut state = cultist_combat(Vec::new());
+    state.draw_pile = strikes(2);
+    run(&mut state, &DrawPileNewCard(card(CardId::Parasite)));
+    assert_eq!(state.draw_pile.len(), 3);
+    run(&mut state, &DrawCards(3));
+    assert_eq!(state.hand.iter().filter(|card| card.card_info.id == CardId::Parasite).count(), 1);
+    assert!(state.legal_choices().iter().all(|choice| match choice {
+      DynAction::PlayCard(PlayCard { card, .. }) => card.card_info.id != CardId::Parasite,
+      _ => true,
+    }));
+
+    end_turn(&mut state);
+    // discarded with the rest of the hand, then reshuffled and drawn again
+    assert!(state.exhaust_pile.is_empty());
+    assert_eq!(state.hand.iter().filter(|card| card.card_info.id == CardId::Parasite).count(), 1);
+  }
+
+  #[test]
+  fn drawing_to_a_hand_size_only_draws_what_is_missing() {
+    let mut state = cultist_combat(strikes(2));
+    state.draw_pile = strikes(10);
+    run(&mut state, &DrawToHandSize(5));
+    assert_eq!(state.hand.len(), 5);
+    assert_eq!(state.draw_pile.len(), 7);
+
+    state.hand.extend(strikes(1));
+    run(&mut state, &DrawToHandSize(5));
+    assert_eq!(state.hand.len(), 6);
+    assert_eq!(state.draw_pile.len(), 7);
+  }
+
+  #[test]
+  fn wrath_doubles_damage_both_ways_and_leaving_calm_gives_energy() {
+    let mut state = cultist_combat(strikes(1));
+    run(&mut state, &ChangeStance(Stance::Wrath));
+    play(&mut state, CardId::StrikeR, 0);
+    assert_eq!(state.monsters[0].creature.hitpoints, 50 - 12);
+    end_turn(&mut state);
+    assert_eq!(state.player.creature.hitpoints, 80 - 12);
+
+    run(&mut state, &ChangeStance(Stance::Calm));
+    run(&mut state, &ChangeStance(Stance::Calm));
+    assert_eq!(state.player.energy, 3);
+    run(&mut state, &ChangeStance(Stance::Neutral));
+    assert_eq!(state.player.energy, 5);
+  }
+
+  #[test]
+  fn debuffs_tick_down_together_at_the_end_of_the_round() {
+    let mut state = combat(vec![monster(MonsterId::Cultist, 50, vec![3])], Vec::new());
+    state.monsters[0].creature.powers.push(power(PowerId::Vulnerable, 1));
+    for &(power_id, amount) in &[(PowerId::Vulnerable, 1), (PowerId::Weak, 2), (PowerId::Frail, 1)] {
+      state.player.creature.powers.push(power(power_id, amount));
+    }
+    end_turn(&mut state);
+    assert_eq!(state.player.creature.powers, vec![power(PowerId::Weak, 1)]);
+    assert!(state.monsters[0].creature.powers.iter().all(|power| power.power_id != PowerId::Vulnerable));
+  }
+
+  #[test]
+  fn buffer_is_only_used_up_by_damage_that_gets_through_block() {
+    let mut state = cultist_combat(Vec::new());
+    state.player.creature.powers.push(power(PowerId::Buffer, 1));
+    state.player.creature.block = 10;
+    end_turn(&mut state);
+    assert_eq!(state.player.creature.hitpoints, 80);
+    assert_eq!(state.player.creature.power_amount(PowerId::Buffer), 1);
+
+    state.player.creature.block = 5;
+    end_turn(&mut state);
+    assert_eq!(state.player.creature.hitpoints, 80);
+    assert!(!state.player.creature.has_power(PowerId::Buffer));
+
+    end_turn(&mut state);
+    assert_eq!(state.player.creature.hitpoints, 74);
+  }
+
+  #[test]
+  fn cards_can_be_added_to_the_top_or_bottom_of_the_draw_pile_or_to_other_piles() {
+    let add = |pile, position| AddCardToPile {
+      card: card(CardId::Dazed),
+      pile,
+      position,
+    };
+    let mut state = cultist_combat(Vec::new());
+    state.draw_pile = strikes(3);
+    run(&mut state, &add(PileLocation::Draw, CardPosition::Top));
+    assert_eq!(state.draw_pile.last(), Some(&card(CardId::Dazed)));
+    assert_eq!(state.draw_pile_known_top, 1);
+
+    // the bottom card is only known if the whole pile is
+    run(&mut state, &add(PileLocation::Draw, CardPosition::Bottom));
+    assert_eq!(state.draw_pile[0], card(CardId::Dazed));
+    assert_eq!(state.draw_pile_known_top, 1);
+    let mut known = cultist_combat(Vec::new());
+    run(&mut known, &add(PileLocation::Draw, CardPosition::Top));
+    run(&mut known, &add(PileLocation::Draw, CardPosition::Bottom));
+    assert_eq!(known.draw_pile_known_top, 2);
+
+    run(&mut state, &add(PileLocation::Discard, CardPosition::Top));
+    run(&mut state, &add(PileLocation::Exhaust, CardPosition::Top));
+    assert_eq!(state.discard_pile, vec![card(CardId::Dazed)]);
+    assert_eq!(state.exhaust_pile, vec![card(CardId::Dazed)]);
+  }
+
+  #[test]
+  fn the_next_turn_draw_modifier_is_used_once() {
+    let mut state = cultist_combat(Vec::new());
+    state.draw_pile = strikes(20);
+    state.next_turn_draw_modifier = -2;
+    end_turn(&mut state);
+    assert_eq!(state.hand.len(), 3);
+    assert_eq!(state.next_turn_draw_modifier, 0);
+    end_turn(&mut state);
+    assert_eq!(state.hand.len(), 5);
+  }
+
+  #[test]
+  fn draw_reduction_from_a_monster_turn_shrinks_the_next_hand() {
+    let mut state = cultist_combat(Vec::new());
+    state.draw_pile = strikes(20);
+    let mut draw_reduction = power(PowerId::DrawReduction, 1);
+    draw_reduction.just_applied = true;
+    state.player.creature.powers.push(draw_reduction);
+    end_turn(&mut state);
+    assert_eq!(state.hand.len(), 4);
+    end_turn(&mut state);
+    assert_eq!(state.hand.len(), 5);
+    assert!(!state.player.creature.has_power(PowerId::DrawReduction));
   }
 }
@@ -1,11 +1,13 @@
 use std::time::{Instant, Duration};
 use ordered_float::OrderedFloat;
 use rand::seq::SliceRandom;
+use rand::Rng;
+use rand_xoshiro::Xoshiro256StarStar;
 
 use crate::actions::*;
 use crate::simulation::*;
 use crate::simulation_state::*;
-use crate::start_and_strategy_ai::{Strategy, FastStrategy, CombatResult, play_out, collect_starting_points};
+use crate::start_and_strategy_ai::{Strategy, FastStrategy, CombatResult, play_out, seeded_play_out, collect_starting_points};
 use crate::neural_net_ai::NeuralStrategy;
 
 
@@ -38,13 +40,13 @@ fn playout_result(state: & CombatState, strategy: & impl Strategy)->CombatResult
 struct MetaStrategy <'a, T>(&'a T);
 
 impl <'a, T: Strategy> Strategy for MetaStrategy <'a, T> {
-  fn choose_choice(&self, state: &CombatState) -> Vec<Choice> {
+  fn choose_choice(&self, state: &CombatState, rng: &mut Xoshiro256StarStar) -> Vec<Choice> {
     let combos = collect_starting_points(state.clone(), 200);
     let choices = combos.into_iter().map(|(mut state, choices)| {
-      run_until_unable(&mut Runner::new(&mut state, true, false));
+      run_until_unable(&mut Runner::with_seed(&mut state, true, false, rng.gen()));
       let num_attempts = 200;
       let score = (0..num_attempts).map (|_| {
-        playout_result(& state, self.0).score
+        CombatResult::new (& seeded_play_out(& state, self.0, rng.gen())).score
       }).sum::<f64>()/num_attempts as f64;
       (choices, score)
     });
@@ -229,11 +231,48 @@ pub fn run_benchmark (name: & str, state: & CombatState, optimization_playouts:
   println!();
 }*/
 
+/// Times the damage calculation for a multi-hit attack while both creatures have several powers,
+/// since every hit runs the give/receive hooks over all of the attacker's and target's powers.
+pub fn damage_calculation_benchmark(state: &CombatState) {
+  let mut state = state.clone();
+  for &power_id in &[PowerId::Strength, PowerId::Dexterity, PowerId::Weak, PowerId::Metallicize, PowerId::Thorns, PowerId::PenNib] {
+    state.player.creature.powers.push(Power {power_id, amount: 2, ..Default::default()});
+  }
+  state.player.creature.powers.sort_by_key(|power| power.power_id.priority());
+  let target = state.monsters.iter().position (| monster | !monster.gone).unwrap();
+  for &power_id in &[PowerId::Vulnerable, PowerId::Strength, PowerId::Artifact] {
+    state.monsters[target].creature.powers.push(Power {power_id, amount: 2, ..Default::default()});
+  }
+  let target = CreatureIndex::Monster(target);
+  
+  let hits = 5;
+  let iterations = 1_000_000;
+  let start = Instant::now();
+  let mut total_damage = 0;
+  for _ in 0..iterations {
+    for _ in 0..hits {
+      let mut info = DamageInfo::new(CreatureIndex::Player, 2, DamageType::Normal);
+      info.apply_powers(&state, CreatureIndex::Player, target, None);
+      total_damage += info.output as i64;
+    }
+  }
+  let elapsed = start.elapsed();
+  println!( "Calculated {} hits in {:.2?} ({:.1} ns per hit, checksum {})", iterations*hits, elapsed, elapsed.as_nanos() as f64/(iterations*hits) as f64, total_damage);
+}
+
+fn hexaghost_state()->CombatState {
+  let ghost_file = std::fs::File::open ("data/hexaghost.json").unwrap();
+  CombatState::from_json_reader (std::io::BufReader::new (ghost_file)).unwrap()
+}
+
+pub fn run_damage_calculation_benchmark() {
+  damage_calculation_benchmark(& hexaghost_state());
+}
+
 pub fn run_benchmarks() {
   let optimization_playouts = 1000000;
   let test_playouts = 10000;
-  let ghost_file = std::fs::File::open ("data/hexaghost.json").unwrap();
-  let ghost_state: CombatState = serde_json::from_reader (std::io::BufReader::new (ghost_file)).unwrap();
+  let ghost_state = hexaghost_state();
   
   let mut fast_random: ExplorationOptimizer<FastStrategy, _> = ExplorationOptimizer::new (|_: &[CandidateStrategy <FastStrategy>] | FastStrategy::random());
   let mut fast_genetic: ExplorationOptimizer<FastStrategy, _> = ExplorationOptimizer::new (| candidates: & [CandidateStrategy <FastStrategy>] | {
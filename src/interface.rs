@@ -238,7 +238,7 @@ pub fn run(root_path: PathBuf) {
   };
 
   if let Ok(file) = std::fs::File::open(root_path.join("last_state.json")) {
-    if let Ok(state) = serde_json::from_reader(std::io::BufReader::new(file)) {
+    if let Ok(state) = CombatState::from_json_reader(std::io::BufReader::new(file)) {
       application_state.set_state(state);
     }
   }
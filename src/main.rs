@@ -94,6 +94,10 @@ fn main() {
     benchmarks::run_benchmarks();
     return
   }
+  if arguments[1] == "damage_benchmark" {
+    benchmarks::run_damage_calculation_benchmark();
+    return
+  }
   
   interface::run(PathBuf::from(arguments[1].clone()));
 
@@ -4,6 +4,7 @@ use enum_map::{Enum, EnumMap};
 use ordered_float::OrderedFloat;
 use rand::seq::SliceRandom;
 use rand::{Rng, random};
+use rand_xoshiro::Xoshiro256StarStar;
 
 use crate::actions::*;
 use crate::simulation::*;
@@ -42,6 +43,8 @@ pub struct NeuralStrategy {
 struct ChoiceWeights {
   play_card_weights: EnumMap <CardId, [Vec<f64>; 2]>,
   end_turn_weights: Vec<f64>,
+  // shared by all the ways of resolving a pending choice, like picking a card to exhaust
+  pending_choice_weights: Vec<f64>,
 }
 
 #[derive (Clone, Debug)]
@@ -61,7 +64,7 @@ struct CombatStateAnalysis {
 
 
 impl Strategy for NeuralStrategy {
-  fn choose_choice (&self, state: & CombatState)->Vec<Choice> {
+  fn choose_choice (&self, state: & CombatState, rng: &mut Xoshiro256StarStar)->Vec<Choice> {
     let analysis = self.analyze (state);
     
     let best_choice = analysis.choices
@@ -87,7 +90,7 @@ impl ChoiceWeights {
       Choice::EndTurn (_) => {
         &self.end_turn_weights
       }
-      _=> panic!("NeuralStrategy doesn't yet know how to handle choices other than playing cards and ending turn"),
+      _=> &self.pending_choice_weights,
     }
   }
   fn get_mut (&mut self, choice: &Choice)-> &mut [f64] {
@@ -98,7 +101,7 @@ impl ChoiceWeights {
       Choice::EndTurn (_) => {
         &mut self.end_turn_weights
       }
-      _=> panic!("NeuralStrategy doesn't yet know how to handle choices other than playing cards and ending turn"),
+      _=> &mut self.pending_choice_weights,
     }
   }
 }
@@ -179,6 +182,7 @@ impl NeuralStrategy {
       random_weights(hidden_layer_size),
     ]);
     let end_turn_weights = random_weights(hidden_layer_size);
+    let pending_choice_weights = random_weights(hidden_layer_size);
     
     NeuralStrategy {
       hidden_layer_size,
@@ -186,6 +190,7 @@ impl NeuralStrategy {
       choice_weights: ChoiceWeights {
         play_card_weights,
         end_turn_weights,
+        pending_choice_weights,
       }
     }
   }
@@ -197,6 +202,7 @@ impl NeuralStrategy {
     
     for weight in result.input_weights.iter_mut().flatten()
       .chain (result.choice_weights.end_turn_weights.iter_mut())
+      .chain (result.choice_weights.pending_choice_weights.iter_mut())
       .chain (result.choice_weights.play_card_weights.iter_mut().flat_map (
         | (id, weights) | weights.iter_mut().flatten()
       )) {
@@ -285,13 +291,15 @@ impl NeuralStrategy {
     
     run_until_unable(&mut runner);
     while !runner.state().combat_over() {
-      let analysis = self.analyze (runner.state());
+      let (state, rng) = runner.state_and_rng();
+      let analysis = self.analyze (state);
     
-      let best_choice = analysis.choices.choose_weighted (&mut rand::thread_rng(), | choice | choice.selection_probability).unwrap().clone();
+      let best_choice = analysis.choices.choose_weighted (rng, | choice | choice.selection_probability).unwrap().clone();
     
+      let pending = runner.state().pending_choice().is_some();
       assert!(runner.state().fresh_subaction_queue.is_empty());
-      assert!(runner.state().stale_subaction_stack.is_empty());
-      assert!(runner.state().actions.is_empty());
+      assert!(runner.state().stale_subaction_stack.is_empty() || pending);
+      assert!(runner.state().actions.is_empty() || pending);
       runner.action_now(& best_choice.choice);
       run_until_unable(&mut runner);
       
@@ -1,15 +1,18 @@
 use derivative::Derivative;
 use serde::{Deserialize, Serialize};
 use smallvec::{smallvec, SmallVec};
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashSet;
 use std::fmt::Write;
+use std::hash::{Hash, Hasher};
 use std::ops::{Add, AddAssign, Mul};
-//use rand::{Rng, SeedableRng};
 use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_xoshiro::Xoshiro256StarStar;
 
 use crate::actions::*;
 pub use crate::simulation_state::cards::CardBehavior;
-pub use crate::simulation_state::monsters::MonsterBehavior;
+pub use crate::simulation_state::monsters::{IntentCache, IntentKind, MonsterBehavior};
 use crate::simulation_state::*;
 
 #[derive(Clone, PartialEq, Serialize, Deserialize, Debug, Derivative)]
@@ -63,15 +66,74 @@ impl Distribution {
   }
 }
 
-/*
+/// See `CombatState::destination_after_play`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum CardDestination {
+  Discard,
+  Exhaust,
+  DrawPileTop,
+  Removed,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, Debug)]
+pub enum PileLocation {
+  Draw,
+  Discard,
+  Hand,
+  Exhaust,
+}
+
+/// Where in the pile an `AddCardToPile` puts the card. Only the draw pile's order matters.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, Debug)]
+pub enum CardPosition {
+  Top,
+  Bottom,
+  Random,
+}
+
+/// What a `ChooseCardsAction` asks the player to pick cards for, which determines both the pile
+/// the cards come from and what happens to them.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, Debug)]
 pub enum CardChoiceType {
+  DiscardCard,
   ExhaustCard,
+  /// Put a card from the hand on top of the draw pile (Warcry).
   HandTopdeck,
+  /// Put a card from the hand on top of the draw pile, costing 0 until it's played (Setup).
+  SetupTopdeck,
+  /// Put a card from the discard pile on top of the draw pile (Headbutt).
   DiscardTopdeck,
+  /// Move a Skill from the draw pile into the hand (Secret Technique).
   TutorSkill,
+  /// Move an Attack from the draw pile into the hand (Secret Weapon).
   TutorAttack,
+  /// Add this many copies of an Attack or Power in the hand to the hand (Dual Wield).
+  Duplicate(i32),
+}
+
+impl CardChoiceType {
+  pub fn pile(self, state: &CombatState) -> &[SingleCard] {
+    match self {
+      CardChoiceType::DiscardTopdeck => &state.discard_pile,
+      CardChoiceType::TutorSkill | CardChoiceType::TutorAttack => &state.draw_pile,
+      _ => &state.hand,
+    }
+  }
+  pub fn allows(self, card: &SingleCard) -> bool {
+    let card_type = card.card_info.card_type;
+    match self {
+      CardChoiceType::TutorSkill => card_type == CardType::Skill,
+      CardChoiceType::TutorAttack => card_type == CardType::Attack,
+      CardChoiceType::Duplicate(_) => card_type == CardType::Attack || card_type == CardType::Power,
+      _ => true,
+    }
+  }
+  /// The indices into `pile()` of all cards that could be chosen.
+  pub fn candidates<'a>(self, state: &'a CombatState) -> impl Iterator<Item = usize> + 'a {
+    let pile = self.pile(state);
+    (0..pile.len()).filter(move |&index| self.allows(&pile[index]))
+  }
 }
-*/
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, Debug)]
 pub enum CreatureIndex {
@@ -86,10 +148,20 @@ pub enum DamageType {
   HitpointLoss,
 }
 
+/// What produced some damage, for hooks that only care about certain cards, moves or powers.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, Debug)]
+pub enum DamageSource {
+  Card(CardId),
+  MonsterMove(i32),
+  Power(PowerId),
+}
+
 #[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, Debug)]
 pub struct DamageInfo {
   pub damage_type: DamageType,
   pub owner: CreatureIndex,
+  #[serde(default)]
+  pub source: Option<DamageSource>,
   pub base: i32,
   pub output: i32,
 }
@@ -98,24 +170,56 @@ impl DamageInfo {
   pub fn new(source: CreatureIndex, base: i32, damage_type: DamageType) -> DamageInfo {
     DamageInfo {
       owner: source,
+      source: None,
       base,
       damage_type,
       output: base,
     }
   }
-  pub fn apply_powers(&mut self, state: &CombatState, owner: CreatureIndex, target: CreatureIndex) {
+  pub fn with_source(mut self, source: DamageSource) -> DamageInfo {
+    self.source = Some(source);
+    self
+  }
+  /// Computes `output` from `base` in the game's order, keeping fractions until the very end:
+  /// 1. the owner's `at_damage_give_flat` hooks (Strength, Vigor), whatever order the powers are in
+  /// 2. the owner's `at_damage_give` multipliers (Weak, Pen Nib), then the player's stance
+  /// 3. the target's `at_damage_receive` hooks (Vulnerable), then the player's stance
+  /// 4. the target's `at_damage_final_receive` hooks (Flight, Intangible)
+  ///
+  /// Only then is the result rounded down, so e.g. a Strike with 3 Strength against a Vulnerable
+  /// target deals floor((6 + 3) * 1.5) = 13.
+  ///
+  /// `card` is the card dealing the damage, for powers that only affect certain cards (like Accuracy).
+  pub fn apply_powers(
+    &mut self,
+    state: &CombatState,
+    owner: CreatureIndex,
+    target: CreatureIndex,
+    card: Option<&SingleCard>,
+  ) {
     self.output = self.base;
     let mut damage = self.output as f64;
     power_hook!(
       state,
       owner,
-      damage = at_damage_give(damage, self.damage_type)
+      damage = at_damage_give_flat(damage, self.damage_type, card)
     );
+    power_hook!(
+      state,
+      owner,
+      damage = at_damage_give(damage, self.damage_type, card)
+    );
+    if owner == CreatureIndex::Player && self.damage_type == DamageType::Normal {
+      damage *= state.player.stance.damage_give_multiplier();
+    }
     power_hook!(
       state,
       target,
       damage = at_damage_receive(damage, self.damage_type)
     );
+    if target == CreatureIndex::Player && self.damage_type == DamageType::Normal {
+      damage *= state.player.stance.damage_receive_multiplier();
+    }
     power_hook!(
       state,
       target,
@@ -159,23 +263,50 @@ pub trait Action: Clone + Into<DynAction> {
   }
 }
 
+/// A snapshot of a `Runner` taken by `Runner::checkpoint`. The action queues live on `CombatState`,
+/// so the state copy covers them too.
+#[derive(Clone, Debug)]
+pub struct RunnerCheckpoint {
+  state: CombatState,
+  rng: Xoshiro256StarStar,
+  log_length: usize,
+}
+
 pub struct Runner<'a> {
   state: &'a mut CombatState,
   allow_random: bool,
   debug: bool,
   log: String,
+  seed: u64,
+  rng: Xoshiro256StarStar,
+  on_step: Option<&'a mut dyn FnMut(&CombatState, &DynAction)>,
 }
 
 impl<'a> Runner<'a> {
   pub fn new(state: &'a mut CombatState, allow_random: bool, debug: bool) -> Self {
+    Runner::with_seed(state, allow_random, debug, rand::random())
+  }
+
+  /// Like `new`, but all random outcomes are drawn from an RNG seeded with `seed`,
+  /// so running the same state with the same seed (and the same choices) always gives the same result.
+  pub fn with_seed(state: &'a mut CombatState, allow_random: bool, debug: bool, seed: u64) -> Self {
     Runner {
       state,
       allow_random,
       debug,
       log: String::new(),
+      seed,
+      rng: Xoshiro256StarStar::seed_from_u64(seed),
+      on_step: None,
     }
   }
 
+  /// Calls `on_step` with the new state after each action is applied, e.g. so a UI can animate the steps.
+  pub fn with_step_observer(mut self, on_step: &'a mut dyn FnMut(&CombatState, &DynAction)) -> Self {
+    self.on_step = Some(on_step);
+    self
+  }
+
   pub fn can_apply_impl(&self, action: &impl Action) -> bool {
     match action.determinism(self.state()) {
       Determinism::Deterministic => true,
@@ -201,13 +332,16 @@ impl<'a> Runner<'a> {
       Determinism::Random(distribution) => {
         let random_value = distribution
           .0
-          .choose_weighted(&mut rand::thread_rng(), |(weight, _)| *weight)
+          .choose_weighted(&mut self.rng, |(weight, _)| *weight)
           .unwrap()
           .1;
         action.execute_random(self, random_value);
       }
       Determinism::Choice => unreachable!(),
     }
+    if let Some(on_step) = &mut self.on_step {
+      on_step(self.state, &action.clone().into());
+    }
     if self.debug {
       writeln!(
         self.log,
@@ -218,6 +352,11 @@ impl<'a> Runner<'a> {
       .unwrap();
     }
   }
+  /// Applies one particular outcome of a random action, for search code that weighs every outcome
+  /// instead of sampling one.
+  pub fn apply_outcome(&mut self, action: &impl Action, random_value: i32) {
+    action.execute_random(self, random_value);
+  }
   pub fn action_now(&mut self, action: &impl Action) {
     if self.state().fresh_subaction_queue.is_empty() && self.can_apply(action) {
       self.apply_impl(action);
@@ -235,17 +374,86 @@ impl<'a> Runner<'a> {
     self.state_mut().actions.push_back(action.into());
   }
 
+  /// Shuffles the whole draw pile with this runner's RNG, forgetting any cards known to be on top.
+  /// Draws already pick a random card, so this mostly matters for code that inspects the pile's order,
+  /// e.g. when simulating a combat from its start rather than from a state read from the game.
+  pub fn shuffle_draw_pile(&mut self) {
+    self.state.draw_pile.shuffle(&mut self.rng);
+    self.state.draw_pile_known_top = 0;
+  }
+
+  pub fn checkpoint(&self) -> RunnerCheckpoint {
+    RunnerCheckpoint {
+      state: self.state.clone(),
+      rng: self.rng.clone(),
+      log_length: self.log.len(),
+    }
+  }
+  /// Restores the state, queues and RNG to what they were at `checkpoint`, so the same random outcomes
+  /// follow again. The debug log is truncated back to that point as well.
+  pub fn rewind(&mut self, checkpoint: &RunnerCheckpoint) {
+    self.state.clone_from(&checkpoint.state);
+    self.rng = checkpoint.rng.clone();
+    self.log.truncate(checkpoint.log_length);
+  }
+
   pub fn state(&self) -> &CombatState {
     self.state
   }
   pub fn state_mut(&mut self) -> &mut CombatState {
     self.state
   }
+  /// The state along with this runner's RNG, for code that makes random choices on the player's behalf
+  /// (like a rollout strategy) and should be reproducible from the same seed.
+  pub fn state_and_rng(&mut self) -> (&CombatState, &mut Xoshiro256StarStar) {
+    (self.state, &mut self.rng)
+  }
+  /// The seed this runner's RNG started from, including the one `new` picks, so that a surprising run
+  /// can be replayed with `with_seed`.
+  pub fn seed(&self) -> u64 {
+    self.seed
+  }
   pub fn debug_log(&self) -> &str {
     &self.log
   }
 }
 
+/// Applies `choice` to a copy of `state` that is written into `scratch`, reusing scratch's allocations,
+/// and runs until the next choice or random outcome.
+pub fn apply_choice_into(scratch: &mut CombatState, state: &CombatState, choice: &Choice, allow_random: bool) {
+  scratch.clone_from(state);
+  let mut runner = Runner::new(scratch, allow_random, false);
+  runner.action_now(choice);
+  run_until_unable(&mut runner);
+}
+
+impl DynAction {
+  /// A cheap estimate of how many outcomes applying this choice can lead to, without running it: the product
+  /// of the number of outcomes of the choice itself and of each action it queues right away (for `EndTurn`,
+  /// each monster's choice of its next move). Random actions further down the line aren't counted.
+  pub fn branch_estimate(&self, state: &CombatState) -> usize {
+    let outcomes = |action: &DynAction| match action.determinism(state) {
+      Determinism::Random(distribution) => distribution.0.len().max(1),
+      _ => 1,
+    };
+    let mut estimate = outcomes(self);
+    match self {
+      DynAction::PlayCard(PlayCard { card, target }) => {
+        for action in cards::card_actions(state, card.clone(), *target) {
+          estimate *= outcomes(&action);
+        }
+      }
+      DynAction::EndTurn(_) => {
+        for index in 0..state.monsters.len() {
+          estimate *= state.predicted_next_intent(index).0.len().max(1);
+        }
+      }
+      _ => {}
+    }
+    estimate
+  }
+}
+
 pub fn run_until_unable(runner: &mut Runner) {
   loop {
     if runner.state().combat_over() {
@@ -271,6 +479,11 @@ pub fn run_until_unable(runner: &mut Runner) {
       }
     }
   }
+
+  let state = runner.state_mut();
+  if state.stale_subaction_stack.is_empty() && state.actions.is_empty() && !state.combat_over() {
+    state.update_intent_caches();
+  }
 }
 
 /*#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -290,6 +503,20 @@ impl Choice {
 
 pub type Choice = DynAction;
 
+impl Player {
+  /// The relics the simulation knows about; see `From<&communication::Relic> for Power`.
+  pub fn relics<'a>(&'a self) -> impl Iterator<Item = &'a Power> + 'a {
+    self
+      .creature
+      .powers
+      .iter()
+      .filter(|power| power.power_id.power_type() == PowerType::Relic)
+  }
+}
+
+// `powers` stays a Vec ordered by `PowerBehavior::priority()`, because the hooks have to run in that order.
+// Creatures rarely have more than a handful of powers, so a linear scan is about as fast as a lookup structure
+// (see `benchmarks::damage_calculation_benchmark`), and it keeps duplicates summing in `power_amount`.
 impl Creature {
   pub fn has_power(&self, power_id: PowerId) -> bool {
     self.powers.iter().any(|power| power.power_id == power_id)
@@ -302,56 +529,363 @@ impl Creature {
       .map(|power| power.amount)
       .sum()
   }
+
+  /// Takes `damage` out of this creature's block, unless it's HP loss, and returns the unblocked rest.
+  pub fn block_damage(&mut self, damage: i32, damage_type: DamageType) -> i32 {
+    if damage_type == DamageType::HitpointLoss {
+      damage
+    } else if damage >= self.block {
+      let unblocked = damage - self.block;
+      self.block = 0;
+      unblocked
+    } else {
+      self.block -= damage;
+      0
+    }
+  }
+  pub fn lose_hitpoints(&mut self, amount: i32) {
+    self.hitpoints = std::cmp::max(0, self.hitpoints - amount);
+  }
+  /// Applies damage directly, returning the unblocked damage dealt. This skips all the power hooks,
+  /// so in combat, damage should go through `DamageAction`, which uses the two halves of this.
+  /// Whoever kills a monster this way is responsible for marking it `gone`.
+  pub fn take_damage(&mut self, damage: i32, damage_type: DamageType) -> i32 {
+    let unblocked = self.block_damage(damage, damage_type);
+    self.lose_hitpoints(unblocked);
+    unblocked
+  }
 }
 
 impl CombatState {
   pub fn combat_over(&self) -> bool {
+    debug_assert_eq!(self.cached_combat_over, self.compute_combat_over());
+    self.cached_combat_over
+  }
+  /// Monsters that escaped are `gone` just like dead ones, so combat only ends once every monster
+  /// has either died or fled.
+  pub fn compute_combat_over(&self) -> bool {
     self.player.creature.hitpoints <= 0 || self.monsters.iter().all(|monster| monster.gone)
   }
+  /// True once combat has ended with the player still standing, whether the monsters died or fled.
+  pub fn combat_won(&self) -> bool {
+    self.combat_over() && self.player.creature.hitpoints > 0
+  }
+  pub fn combat_lost(&self) -> bool {
+    self.player.creature.hitpoints <= 0
+  }
+
+  /// A hash of everything that matters for play, for transposition tables. Like `PartialEq`, it treats
+  /// piles as unordered, except for the cards known to be on top of the draw pile, which are hashed in order.
+  /// It uses a fixed-key hasher rather than `RandomState`, so it's the same across runs.
+  pub fn canonical_hash(&self) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    self.hash(&mut hasher);
+    self.draw_pile[self.draw_pile.len() - self.draw_pile_known_top..].hash(&mut hasher);
+    hasher.finish()
+  }
+
+  /// Human-readable differences between two states, for debugging surprising rollouts.
+  /// Piles are compared as unordered multisets, like `PartialEq` does.
+  pub fn diff(&self, other: &CombatState) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut compare = |name: String, before: String, after: String| {
+      if before != after {
+        result.push(format!("{}: {} -> {}", name, before, after));
+      }
+    };
+    let sorted_pile = |pile: &[SingleCard]| {
+      let mut sorted: Vec<_> = pile.iter().collect();
+      sorted.sort();
+      format!("{:?}", sorted)
+    };
+
+    compare(
+      "player".to_string(),
+      format!("{:?}", self.player.creature),
+      format!("{:?}", other.player.creature),
+    );
+    compare(
+      "energy".to_string(),
+      self.player.energy.to_string(),
+      other.player.energy.to_string(),
+    );
+    for index in 0..self.monsters.len().max(other.monsters.len()) {
+      let describe = |state: &CombatState| match state.monsters.get(index) {
+        Some(monster) if !monster.gone => format!("{:?} {:?}", monster.monster_id, monster.creature),
+        Some(monster) => format!("{:?} (gone)", monster.monster_id),
+        None => "(none)".to_string(),
+      };
+      compare(format!("monster {}", index), describe(self), describe(other));
+    }
+    for (name, before, after) in [
+      ("hand", &self.hand[..], &other.hand[..]),
+      ("draw pile", &self.draw_pile[..], &other.draw_pile[..]),
+      ("discard pile", &self.discard_pile[..], &other.discard_pile[..]),
+      ("exhaust pile", &self.exhaust_pile[..], &other.exhaust_pile[..]),
+    ] {
+      compare(name.to_string(), sorted_pile(before), sorted_pile(after));
+    }
+    compare(
+      "turn".to_string(),
+      self.turn_number.to_string(),
+      other.turn_number.to_string(),
+    );
+    result
+  }
+  pub fn update_combat_over(&mut self) {
+    self.cached_combat_over = self.compute_combat_over();
+  }
+  /// Reads a state saved as JSON, recomputing `cached_combat_over`, which isn't saved.
+  pub fn from_json_reader(reader: impl std::io::Read) -> serde_json::Result<CombatState> {
+    let mut result: CombatState = serde_json::from_reader(reader)?;
+    result.update_combat_over();
+    Ok(result)
+  }
+
+  pub fn gold(&self) -> i32 {
+    self.starting_gold + self.gold_gained
+  }
+
+  pub fn add_repeat_next(&mut self, card_type: CardType, count: u32) {
+    if let Some(existing) = self
+      .repeat_next
+      .iter_mut()
+      .find(|(existing_type, _)| *existing_type == card_type)
+    {
+      existing.1 += count;
+    } else {
+      self.repeat_next.push((card_type, count));
+    }
+  }
+
+  /// Uses up one repeat for `card_type`, returning whether there was one.
+  pub fn take_repeat_next(&mut self, card_type: CardType) -> bool {
+    if let Some(index) = self
+      .repeat_next
+      .iter()
+      .position(|&(existing_type, _)| existing_type == card_type)
+    {
+      self.repeat_next[index].1 -= 1;
+      if self.repeat_next[index].1 == 0 {
+        self.repeat_next.remove(index);
+      }
+      true
+    } else {
+      false
+    }
+  }
+
+  /// The energy it would currently cost to play `card`, taking effects like Corruption into account.
+  pub fn effective_cost(&self, card: &SingleCard) -> i32 {
+    let cost = card.cost_for_turn.unwrap_or(card.cost);
+    if cost > 0
+      && card.card_info.card_type == CardType::Skill
+      && self.player.creature.has_power(PowerId::Corruption)
+    {
+      0
+    } else {
+      cost
+    }
+  }
+
+  /// Where `card` goes once it's done being played. The first rule that applies wins:
+  /// 1. Powers are removed from combat.
+  /// 2. Cards that exhaust, and Skills under Corruption, are exhausted.
+  /// 3. With Rebound, the card goes on top of the draw pile.
+  /// 4. Everything else is discarded.
+  ///
+  /// Effects that make extra copies of the card, like Anger, don't change where the card itself goes.
+  pub fn destination_after_play(&self, card: &SingleCard) -> CardDestination {
+    let card_type = card.card_info.card_type;
+    if card_type == CardType::Power {
+      CardDestination::Removed
+    } else if card.exhausts()
+      || (card_type == CardType::Skill && self.player.creature.has_power(PowerId::Corruption))
+    {
+      CardDestination::Exhaust
+    } else if self.player.creature.has_power(PowerId::Rebound) {
+      CardDestination::DrawPileTop
+    } else {
+      CardDestination::Discard
+    }
+  }
 
   pub fn card_playable(&self, card: &SingleCard) -> bool {
     assert!(X_COST == -1);
     assert!(UNPLAYABLE == -2);
     card.cost >= -1
-      && self.player.energy >= card.cost
+      && self.player.energy >= self.effective_cost(card)
       && card.card_info.id.playable(self)
       && !(card.card_info.card_type == CardType::Attack
         && self.player.creature.has_power(PowerId::Entangled))
   }
 
-  pub fn legal_choices(&self) -> Vec<Choice> {
-    let mut result = Vec::with_capacity(10);
-    result.push(EndTurn.into());
-    for (index, card) in self.hand.iter().enumerate() {
-      if self.hand[..index]
-        .iter()
-        .all(|earlier_card| earlier_card != card)
-        && self.card_playable(card)
-      {
-        if card.card_info.has_target {
-          for (monster_index, monster) in self.monsters.iter().enumerate() {
-            if !monster.gone {
-              result.push(
-                PlayCard {
-                  card: card.clone(),
-                  target: monster_index,
-                }
-                .into(),
-              );
-            }
-          }
-        } else {
-          result.push(
-            PlayCard {
-              card: card.clone(),
-              target: 0,
-            }
-            .into(),
-          );
-        }
-      }
+  /// Adds `card` to the hand, or to the discard pile if the hand already holds 10 cards, as in the game.
+  /// Returns whether the card ended up in the hand.
+  pub fn add_card_to_hand(&mut self, card: SingleCard) -> bool {
+    if self.hand.len() >= 10 {
+      self.discard_pile.push(card);
+      false
+    } else {
+      self.hand.push(card);
+      true
     }
-    result
+  }
+  /// Puts `card` on top of the draw pile, so it is known to be the next card drawn.
+  pub fn put_on_top_of_draw_pile(&mut self, card: SingleCard) {
+    self.draw_pile.push(card);
+    self.draw_pile_known_top += 1;
+  }
+  /// Shuffles `card` into the draw pile: it goes below any cards known to be on top,
+  /// among the cards that are drawn in random order.
+  pub fn add_card_to_draw_pile(&mut self, card: SingleCard) {
+    let index = self.draw_pile.len() - self.draw_pile_known_top;
+    self.draw_pile.insert(index, card);
+  }
+  pub fn remove_from_draw_pile(&mut self, index: usize) -> SingleCard {
+    if index >= self.draw_pile.len() - self.draw_pile_known_top {
+      self.draw_pile_known_top -= 1;
+    }
+    self.draw_pile.remove(index)
+  }
+
+  /// Every card in combat: the hand, draw pile, discard pile, exhaust pile, limbo and the card being played.
+  pub fn all_cards<'a>(&'a self) -> impl Iterator<Item = &'a SingleCard> + 'a {
+    self
+      .hand
+      .iter()
+      .chain(self.draw_pile.iter())
+      .chain(self.discard_pile.iter())
+      .chain(self.exhaust_pile.iter())
+      .chain(self.limbo.iter())
+      .chain(self.card_in_play.iter())
+  }
+  pub fn all_cards_mut<'a>(&'a mut self) -> impl Iterator<Item = &'a mut SingleCard> + 'a {
+    self
+      .hand
+      .iter_mut()
+      .chain(self.draw_pile.iter_mut())
+      .chain(self.discard_pile.iter_mut())
+      .chain(self.exhaust_pile.iter_mut())
+      .chain(self.limbo.iter_mut())
+      .chain(self.card_in_play.iter_mut())
+  }
+
+  /// Each distinct card in hand, in hand order.
+  pub fn distinct_hand_cards<'a>(&'a self) -> impl Iterator<Item = &'a SingleCard> + 'a {
+    self
+      .hand
+      .iter()
+      .enumerate()
+      .filter(move |&(index, card)| {
+        self.hand[..index]
+          .iter()
+          .all(|earlier_card| earlier_card != card)
+      })
+      .map(|(_, card)| card)
+  }
+
+  /// Each distinct card in hand that can currently be played, in hand order.
+  pub fn distinct_playable_cards<'a>(&'a self) -> impl Iterator<Item = &'a SingleCard> + 'a {
+    self
+      .distinct_hand_cards()
+      .filter(move |card| self.card_playable(card))
+  }
+
+  /// The `target` values that playing `card` could use; untargeted cards always use target 0.
+  pub fn card_targets<'a>(&'a self, card: &SingleCard) -> impl Iterator<Item = usize> + 'a {
+    let has_target = card.card_info.has_target;
+    let count = if has_target { self.monsters.len() } else { 1 };
+    (0..count).filter(move |&target| !has_target || !self.monsters[target].gone)
+  }
+
+  /// Every queued action in the order `run_until_unable` will get to them (assuming nothing new is queued),
+  /// e.g. for showing what's about to happen. The queues themselves are public fields too.
+  pub fn upcoming_actions<'a>(&'a self) -> impl Iterator<Item = &'a DynAction> + 'a {
+    self
+      .fresh_subaction_queue
+      .iter()
+      .chain(self.stale_subaction_stack.iter().rev())
+      .chain(self.actions.iter())
+  }
+
+  /// Whether a driver loop should ask for the player's next choice: it's the player's turn, combat isn't
+  /// over, and `run_until_unable` has nothing left to do except wait on a pending choice, if any.
+  /// After `run_until_unable` with randomness allowed, this is only false once combat is over.
+  pub fn player_in_control(&self) -> bool {
+    !self.turn_has_ended
+      && !self.combat_over()
+      && self.fresh_subaction_queue.is_empty()
+      && ((self.stale_subaction_stack.is_empty() && self.actions.is_empty()) || self.pending_choice().is_some())
+  }
+
+  /// The action waiting for the player to choose something (like which card to discard), if any.
+  /// While one is pending, the only legal choices are the ways of resolving it.
+  pub fn pending_choice(&self) -> Option<&DynAction> {
+    self
+      .stale_subaction_stack
+      .last()
+      .filter(|action| action.determinism(self) == Determinism::Choice)
+  }
+
+  fn pending_choices_iter<'a>(&'a self) -> impl Iterator<Item = Choice> + 'a {
+    let pending = match self.pending_choice() {
+      Some(DynAction::ChooseCardsAction(action)) => Some(action.clone()),
+      _ => None,
+    };
+    pending.into_iter().flat_map(move |action| {
+      let choice_type = action.choice_type;
+      let pile = choice_type.pile(self);
+      // identical cards lead to identical states, so only offer the first of each
+      let cards = choice_type
+        .candidates(self)
+        .filter(move |&index| !pile[..index].contains(&pile[index]))
+        .map(move |card_index| Choice::from(ChooseCard { choice_type, card_index }));
+      let stop = if action.any_number {
+        Some(Choice::from(StopChoosingCards))
+      } else {
+        None
+      };
+      cards.chain(stop)
+    })
+  }
+
+  fn turn_choices_iter<'a>(&'a self) -> impl Iterator<Item = Choice> + 'a {
+    std::iter::once(Choice::from(EndTurn)).chain(self.distinct_playable_cards().flat_map(
+      move |card| {
+        self.card_targets(card).map(move |target| {
+          Choice::from(PlayCard {
+            card: card.clone(),
+            target,
+          })
+        })
+      },
+    ))
+  }
+
+  pub fn legal_choices_iter<'a>(&'a self) -> impl Iterator<Item = Choice> + 'a {
+    let turn_choices = if self.pending_choice().is_none() {
+      Some(self.turn_choices_iter())
+    } else {
+      None
+    };
+    self
+      .pending_choices_iter()
+      .chain(turn_choices.into_iter().flatten())
+  }
+
+  pub fn legal_choice_count(&self) -> usize {
+    if self.pending_choice().is_some() {
+      return self.pending_choices_iter().count();
+    }
+    1 + self
+      .distinct_playable_cards()
+      .map(|card| self.card_targets(card).count())
+      .sum::<usize>()
+  }
+
+  pub fn legal_choices(&self) -> Vec<Choice> {
+    self.legal_choices_iter().collect()
   }
 
   pub fn get_creature(&self, index: CreatureIndex) -> &Creature {
@@ -371,6 +905,59 @@ impl CombatState {
   pub fn monster_intent(&self, monster_index: usize) -> i32 {
     self.monsters[monster_index].intent()
   }
+
+  /// A hash of everything `classify_intent` reads for this monster: the monster itself, plus the player's
+  /// powers and stance (which change the damage) and the turn number and player hitpoints (which some intents use).
+  fn intent_inputs(&self, monster_index: usize) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    self.monsters[monster_index].hash(&mut hasher);
+    self.player.creature.powers.hash(&mut hasher);
+    self.player.creature.hitpoints.hash(&mut hasher);
+    self.player.stance.hash(&mut hasher);
+    self.turn_number.hash(&mut hasher);
+    hasher.finish()
+  }
+
+  pub fn intent_kind(&self, monster_index: usize) -> IntentKind {
+    match self.monsters[monster_index].intent_cache {
+      Some(cache) if cache.inputs == self.intent_inputs(monster_index) => cache.kind,
+      _ => monsters::classify_intent(self, monster_index),
+    }
+  }
+
+  /// The probabilities of each move id the monster could choose after doing its current intent,
+  /// given its move history so far. Moves that depend on the rest of the state (like the turn number)
+  /// are predicted from the state as it is now.
+  pub fn predicted_next_intent(&self, monster_index: usize) -> Distribution {
+    if self.monsters[monster_index].gone {
+      Distribution::new()
+    } else {
+      monsters::intent_choice_distribution(self, monster_index)
+    }
+  }
+
+  pub fn incoming_damage(&self) -> i32 {
+    (0..self.monsters.len())
+      .filter(|&index| !self.monsters[index].gone)
+      .map(|index| self.intent_kind(index).incoming_damage())
+      .sum()
+  }
+
+  /// Reclassifies the intents whose inputs changed since they were cached; the others are kept as they are.
+  pub fn update_intent_caches(&mut self) {
+    for index in 0..self.monsters.len() {
+      let monster = &self.monsters[index];
+      if monster.gone || monster.move_history.is_empty() {
+        self.monsters[index].intent_cache = None;
+        continue;
+      }
+      let inputs = self.intent_inputs(index);
+      if monster.intent_cache.map_or(true, |cache| cache.inputs != inputs) {
+        let kind = monsters::classify_intent(self, index);
+        self.monsters[index].intent_cache = Some(IntentCache { inputs, kind });
+      }
+    }
+  }
 }
 
 impl Monster {
@@ -384,3 +971,332 @@ impl Monster {
     self.move_history.push(intent);
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::simulation_state::testing::*;
+
+  #[test]
+  fn intent_caches_follow_changes_to_their_inputs() {
+    let mut state = combat(vec![monster(MonsterId::Cultist, 50, vec![1])], Vec::new());
+    state.update_intent_caches();
+    let attack = IntentKind::Attack { damage: 6, hits: 1 };
+    assert_eq!(state.monsters[0].intent_cache.unwrap().kind, attack);
+    assert_eq!(state.intent_kind(0), attack);
+
+    run(
+      &mut state,
+      &ApplyPowerAction {
+        source: CreatureIndex::Monster(0),
+        target: CreatureIndex::Monster(0),
+        power_id: PowerId::Strength,
+        amount: 2,
+      },
+    );
+    let stronger = IntentKind::Attack { damage: 8, hits: 1 };
+    assert_eq!(state.monsters[0].intent_cache.unwrap().kind, stronger);
+
+    // a cache that wasn't refreshed after a change isn't used
+    state.player.creature.powers.push(power(PowerId::Vulnerable, 1));
+    assert_eq!(state.intent_kind(0), IntentKind::Attack { damage: 12, hits: 1 });
+  }
+
+  #[test]
+  fn strength_is_added_before_vulnerable_multiplies() {
+    let mut cultist = monster(MonsterId::Cultist, 50, vec![1]);
+    cultist.creature.powers.push(power(PowerId::Vulnerable, 1));
+    let mut state = combat(vec![cultist], vec![card(CardId::StrikeR)]);
+    // Weak comes first in the list, but Strength still applies before it
+    state.player.creature.powers.push(power(PowerId::Weak, 1));
+    state.player.creature.powers.push(power(PowerId::Strength, 3));
+    let mut info = DamageInfo::new(CreatureIndex::Player, 6, DamageType::Normal);
+    info.apply_powers(&state, CreatureIndex::Player, CreatureIndex::Monster(0), None);
+    // floor((6 + 3) * 0.75 * 1.5) = 10
+    assert_eq!(info.output, 10);
+
+    state.player.creature.powers.remove(0);
+    play(&mut state, CardId::StrikeR, 0);
+    assert_eq!(state.monsters[0].creature.hitpoints, 50 - 13);
+  }
+
+  #[test]
+  fn a_block_only_intent_adds_no_incoming_damage() {
+    let mut state = combat(vec![monster(MonsterId::Looter, 45, vec![1, 2])], Vec::new());
+    assert_eq!(state.intent_kind(0), IntentKind::Defend);
+    assert_eq!(state.incoming_damage(), 0);
+
+    end_turn(&mut state);
+    assert_eq!(state.monsters[0].creature.block, 6);
+    assert_eq!(state.player.creature.hitpoints, 80);
+  }
+
+  #[test]
+  fn only_a_random_monster_move_makes_more_than_one_branch() {
+    let state = combat(vec![monster(MonsterId::JawWorm, 40, vec![1])], vec![card(CardId::StrikeR)]);
+    let strike = Choice::from(PlayCard {
+      card: card(CardId::StrikeR),
+      target: 0,
+    });
+    assert_eq!(strike.branch_estimate(&state), 1);
+    assert!(Choice::from(EndTurn).branch_estimate(&state) > 1);
+  }
+
+  #[test]
+  fn a_saved_finished_combat_is_still_over_when_loaded() {
+    let mut state = combat(vec![monster(MonsterId::Cultist, 50, vec![1])], Vec::new());
+    state.monsters[0].creature.hitpoints = 0;
+    state.monsters[0].gone = true;
+    state.update_combat_over();
+    let saved = serde_json::to_string(&state).unwrap();
+    let loaded = CombatState::from_json_reader(saved.as_bytes()).unwrap();
+    assert!(loaded.combat_over());
+    assert_eq!(loaded, state);
+  }
+
+  #[test]
+  fn the_choice_iterator_matches_the_collected_choices() {
+    let state = combat(
+      vec![
+        monster(MonsterId::Cultist, 50, vec![1]),
+        monster(MonsterId::JawWorm, 40, vec![1]),
+      ],
+      vec![card(CardId::StrikeR), card(CardId::StrikeR), card(CardId::DefendR), card(CardId::Bash)],
+    );
+    let collected = state.legal_choices();
+    assert_eq!(state.legal_choices_iter().collect::<Vec<_>>(), collected);
+    // EndTurn, Strike and Bash at each monster, Defend
+    assert_eq!(collected.len(), 6);
+    assert_eq!(state.legal_choice_count(), collected.len());
+  }
+
+  #[test]
+  fn diff_lists_what_a_strike_changed() {
+    let before = combat(vec![monster(MonsterId::Cultist, 50, vec![3])], vec![card(CardId::StrikeR)]);
+    let mut after = before.clone();
+    play(&mut after, CardId::StrikeR, 0);
+    let diff = before.diff(&after);
+    assert!(diff.contains(&"energy: 3 -> 2".to_string()));
+    assert!(diff.contains(&"monster 0: Cultist 50/50 -> Cultist 44/50".to_string()));
+    assert!(diff.iter().any(|line| line.starts_with("hand: ")));
+    assert!(diff.iter().any(|line| line.starts_with("discard pile: ")));
+    assert!(!diff.iter().any(|line| line.starts_with("player: ")));
+    assert!(before.diff(&before).is_empty());
+  }
+
+  #[test]
+  fn shuffling_the_draw_pile_is_a_seeded_permutation() {
+    let mut state = combat(vec![monster(MonsterId::Cultist, 50, vec![3])], Vec::new());
+    state.draw_pile = [CardId::StrikeR, CardId::DefendR, CardId::Bash, CardId::Anger]
+      .iter()
+      .cycle()
+      .take(12)
+      .map(|&id| card(id))
+      .collect();
+    state.draw_pile_known_top = 2;
+    let shuffled = |seed| {
+      let mut result = state.clone();
+      Runner::with_seed(&mut result, true, false, seed).shuffle_draw_pile();
+      result
+    };
+    let first = shuffled(3);
+    assert_eq!(first.draw_pile, shuffled(3).draw_pile);
+    assert_eq!(first.draw_pile_known_top, 0);
+
+    let sorted = |pile: &[SingleCard]| {
+      let mut result = pile.to_vec();
+      result.sort();
+      result
+    };
+    assert_eq!(sorted(&first.draw_pile), sorted(&state.draw_pile));
+    assert_eq!(sorted(&shuffled(4).draw_pile), sorted(&state.draw_pile));
+  }
+
+  #[test]
+  fn the_hash_ignores_the_order_of_unseen_draw_pile_cards() {
+    let mut state = combat(vec![monster(MonsterId::Cultist, 50, vec![3])], Vec::new());
+    state.draw_pile = vec![card(CardId::StrikeR), card(CardId::DefendR), card(CardId::Bash)];
+    let mut reordered = state.clone();
+    reordered.draw_pile.reverse();
+    assert_eq!(state.canonical_hash(), reordered.canonical_hash());
+
+    // once the top card is known, it matters which one it is
+    state.draw_pile_known_top = 1;
+    reordered.draw_pile_known_top = 1;
+    assert_ne!(state.canonical_hash(), reordered.canonical_hash());
+
+    reordered.draw_pile = vec![card(CardId::DefendR), card(CardId::StrikeR), card(CardId::Bash)];
+    assert_eq!(state.canonical_hash(), reordered.canonical_hash());
+  }
+
+  #[test]
+  fn rewinding_to_a_checkpoint_replays_the_same_outcomes() {
+    let mut state = combat(vec![monster(MonsterId::JawWorm, 40, vec![1])], Vec::new());
+    state.draw_pile = (0..5)
+      .map(|_| card(CardId::StrikeR))
+      .chain((0..5).map(|_| card(CardId::DefendR)))
+      .collect();
+    let start = state.clone();
+    let mut runner = Runner::with_seed(&mut state, true, true, 9);
+    let checkpoint = runner.checkpoint();
+    runner.action_now(&EndTurn);
+    run_until_unable(&mut runner);
+    let first = (runner.state().clone(), runner.debug_log().to_string());
+
+    runner.rewind(&checkpoint);
+    assert_eq!(runner.state(), &start);
+    assert!(runner.debug_log().is_empty());
+    runner.action_now(&EndTurn);
+    run_until_unable(&mut runner);
+    assert_eq!(runner.state().hand.to_vec(), first.0.hand.to_vec());
+    assert_eq!(runner.state().monsters[0].move_history, first.0.monsters[0].move_history);
+    assert_eq!(runner.debug_log(), first.1);
+  }
+
+  #[test]
+  fn runners_with_the_same_seed_log_the_same_actions() {
+    let mut state = combat(vec![monster(MonsterId::JawWorm, 40, vec![1])], Vec::new());
+    state.draw_pile = (0..5)
+      .map(|_| card(CardId::StrikeR))
+      .chain((0..5).map(|_| card(CardId::DefendR)))
+      .collect();
+    let log = |seed| {
+      let mut result = state.clone();
+      let mut runner = Runner::with_seed(&mut result, true, true, seed);
+      for _ in 0..3 {
+        runner.action_now(&EndTurn);
+        run_until_unable(&mut runner);
+      }
+      assert_eq!(runner.seed(), seed);
+      runner.debug_log().to_string()
+    };
+    let first = log(5);
+    assert!(!first.is_empty());
+    assert_eq!(first, log(5));
+  }
+
+  #[test]
+  fn upcoming_actions_lists_queued_actions_in_order() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![3])],
+      vec![upgraded(CardId::TrueGrit), card(CardId::StrikeR), card(CardId::DefendR)],
+    );
+    assert_eq!(state.upcoming_actions().count(), 0);
+    let mut runner = Runner::new(&mut state, false, false);
+    runner.action_bottom(DrawCards(1));
+    runner.action_bottom(GainEnergyAction(1));
+    drop(runner);
+    let expected: Vec<DynAction> = vec![DrawCards(1).into(), GainEnergyAction(1).into()];
+    assert_eq!(state.upcoming_actions().cloned().collect::<Vec<_>>(), expected);
+
+    state.actions.clear();
+    play(&mut state, CardId::TrueGrit, 0);
+    assert_eq!(state.upcoming_actions().next(), state.pending_choice());
+    assert!(state.pending_choice().is_some());
+  }
+
+  #[test]
+  fn equal_choices_hash_and_compare_equal() {
+    let hash = |choice: &Choice| {
+      let mut hasher = DefaultHasher::new();
+      choice.hash(&mut hasher);
+      hasher.finish()
+    };
+    let strike = |target| {
+      Choice::from(PlayCard {
+        card: card(CardId::StrikeR),
+        target,
+      })
+    };
+    assert_eq!(strike(0), strike(0));
+    assert_eq!(hash(&strike(0)), hash(&strike(0)));
+    assert_ne!(strike(0), strike(1));
+
+    let mut choices = vec![strike(1), Choice::from(EndTurn), strike(0), strike(1)];
+    choices.sort();
+    choices.dedup();
+    assert_eq!(choices.len(), 3);
+    assert!(choices.windows(2).all(|pair| pair[0] < pair[1]));
+  }
+
+  #[test]
+  fn the_player_stays_in_control_through_a_discard_choice() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![3])],
+      vec![card(CardId::Acrobatics), card(CardId::StrikeR)],
+    );
+    state.draw_pile = (0..5).map(|_| card(CardId::DefendR)).collect();
+    assert!(state.player_in_control());
+    play(&mut state, CardId::Acrobatics, 0);
+    assert!(state.pending_choice().is_some());
+    assert!(state.player_in_control());
+    assert!(state
+      .legal_choices()
+      .iter()
+      .all(|choice| matches!(choice, DynAction::ChooseCard(ChooseCard { choice_type: CardChoiceType::DiscardCard, .. }))));
+
+    let choice = state.legal_choices()[0].clone();
+    run(&mut state, &choice);
+    assert!(state.pending_choice().is_none());
+    assert!(state.player_in_control());
+
+    state.player.creature.hitpoints = 0;
+    state.update_combat_over();
+    assert!(!state.player_in_control());
+  }
+
+  #[test]
+  fn take_damage_uses_block_first_and_stops_at_0_hitpoints() {
+    let mut creature = Creature {
+      hitpoints: 20,
+      max_hitpoints: 20,
+      block: 10,
+      powers: Vec::new(),
+    };
+    assert_eq!(creature.take_damage(6, DamageType::Normal), 0);
+    assert_eq!((creature.block, creature.hitpoints), (4, 20));
+    assert_eq!(creature.take_damage(10, DamageType::Normal), 6);
+    assert_eq!((creature.block, creature.hitpoints), (0, 14));
+
+    creature.block = 5;
+    assert_eq!(creature.take_damage(3, DamageType::HitpointLoss), 3);
+    assert_eq!((creature.block, creature.hitpoints), (5, 11));
+    // the returned amount isn't reduced by overkill, but hitpoints stop at 0
+    assert_eq!(creature.take_damage(30, DamageType::Normal), 25);
+    assert_eq!((creature.block, creature.hitpoints), (0, 0));
+  }
+
+  #[test]
+  fn played_cards_are_routed_by_the_first_rule_that_applies() {
+    let mut state = combat(vec![monster(MonsterId::Cultist, 50, vec![3])], Vec::new());
+    let destination = |state: &CombatState, id| state.destination_after_play(&card(id));
+    assert_eq!(destination(&state, CardId::StrikeR), CardDestination::Discard);
+    assert_eq!(destination(&state, CardId::Shiv), CardDestination::Exhaust);
+    assert_eq!(destination(&state, CardId::Inflame), CardDestination::Removed);
+    assert_eq!(destination(&state, CardId::DefendR), CardDestination::Discard);
+
+    state.player.creature.powers.push(power(PowerId::Rebound, 1));
+    assert_eq!(destination(&state, CardId::StrikeR), CardDestination::DrawPileTop);
+    assert_eq!(destination(&state, CardId::Shiv), CardDestination::Exhaust);
+    assert_eq!(destination(&state, CardId::Inflame), CardDestination::Removed);
+
+    state.player.creature.powers.push(power(PowerId::Corruption, -1));
+    assert_eq!(destination(&state, CardId::DefendR), CardDestination::Exhaust);
+  }
+
+  #[test]
+  fn all_cards_mut_reaches_every_pile() {
+    let mut state = combat(vec![monster(MonsterId::Cultist, 50, vec![3])], vec![card(CardId::StrikeR)]);
+    state.draw_pile.push(card(CardId::StrikeR));
+    state.discard_pile.push(card(CardId::StrikeR));
+    state.exhaust_pile.push(card(CardId::StrikeR));
+    state.limbo.push(card(CardId::StrikeR));
+    state.card_in_play = Some(card(CardId::StrikeR));
+    for card in state.all_cards_mut() {
+      card.upgrade();
+    }
+    assert_eq!(state.all_cards().count(), 6);
+    assert!(state.all_cards().all(|card| card.upgrades == 1));
+    assert_eq!(state.card_in_play, Some(upgraded(CardId::StrikeR)));
+  }
+}
@@ -14,6 +14,8 @@ use crate::simulation::*;
 pub mod cards;
 pub mod monsters;
 pub mod powers;
+#[cfg(test)]
+pub mod testing;
 
 pub use cards::CardId;
 pub use monsters::MonsterId;
@@ -37,8 +39,10 @@ pub fn compare_cards_unordered(first: &[SingleCard], second: &[SingleCard]) -> b
   first_sorted == second_sorted
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug, Derivative)]
-#[derivative(PartialEq, Eq, Hash)]
+// clone_from reuses the existing piles' allocations, so search code can keep a scratch state around
+// and `clone_from` into it instead of allocating a fresh state for every rollout.
+#[derive(Serialize, Deserialize, Debug, Derivative)]
+#[derivative(Clone(clone_from = "true"), PartialEq, Eq, Hash)]
 pub struct CombatState {
   #[derivative(
     PartialEq(compare_with = "compare_cards_unordered"),
@@ -70,16 +74,52 @@ pub struct CombatState {
   pub monsters: ArrayVec<[Monster; MAX_MONSTERS]>,
   pub turn_number: i32,
   pub turn_has_ended: bool,
+  /// Cached result of `compute_combat_over()`; kept up to date by the actions that change hitpoints or `gone`.
+  /// It isn't saved, so load states with `CombatState::from_json_reader`, which recomputes it.
+  #[serde(skip)]
+  #[derivative(PartialEq = "ignore", Hash = "ignore")]
+  pub cached_combat_over: bool,
+  /// How many cards at the end of `draw_pile` were put on top in a known order (e.g. by Warcry);
+  /// these are drawn last-first before the rest of the pile, which is drawn in random order.
+  #[serde(default)]
+  pub draw_pile_known_top: usize,
+  /// Added to the number of cards drawn at the start of the player's next turn, then reset to 0.
+  #[serde(default)]
+  pub next_turn_draw_modifier: i32,
+  /// Actions waiting for a number of the player's turns to end, like The Bomb.
+  #[serde(default)]
+  pub delayed_actions: Vec<DelayedAction>,
+  /// How many more cards of each type will be played twice this turn (Double Tap, Burst, Amplify).
+  #[serde(default)]
+  pub repeat_next: Vec<(CardType, u32)>,
+  /// The player's gold when the state was read from the game.
+  #[serde(default)]
+  pub starting_gold: i32,
+  /// Gold gained (or, if negative, lost) during this combat.
+  #[serde(default)]
+  pub gold_gained: i32,
+  /// Every relic the player had when the state was read from the game; see `Relic`.
+  #[serde(default)]
+  pub relics: Vec<Relic>,
 
   pub fresh_subaction_queue: Vec<DynAction>,
   pub stale_subaction_stack: Vec<DynAction>,
   pub actions: VecDeque<DynAction>,
 }
 
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub struct DelayedAction {
+  pub turns_left: i32,
+  pub action: DynAction,
+}
+
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct SingleCard {
   pub misc: i32,
   pub cost: i32,
+  /// Overrides `cost` until the card leaves the hand or the turn ends (e.g. Infernal Blade's 0).
+  #[serde(default)]
+  pub cost_for_turn: Option<i32>,
   pub upgrades: i32,
   pub card_info: Arc<CardInfo>,
 }
@@ -102,6 +142,17 @@ pub enum Rarity {
   Special,
 }
 
+/// Which card pool a card belongs to, like the game's card colors.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Debug)]
+pub enum CardClass {
+  Red,
+  Green,
+  Blue,
+  Purple,
+  Colorless,
+  Curse,
+}
+
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Debug)]
 pub struct CardInfo {
   pub id: CardId,
@@ -110,8 +161,14 @@ pub struct CardInfo {
   pub normal_cost: i32,
   pub upgraded_cost: i32,
   pub ethereal: bool,
+  pub retain: bool,
+  /// Whether the card has the game's STRIKE tag (Strike Dummy, Perfected Strike).
+  pub strike: bool,
   pub has_target: bool,
   pub exhausts: bool,
+  /// Overrides `exhausts` once the card is upgraded (Calculated Gamble+ doesn't exhaust).
+  pub upgraded_exhausts: Option<bool>,
+  pub class: CardClass,
 }
 
 impl Default for CardInfo {
@@ -123,13 +180,18 @@ impl Default for CardInfo {
       normal_cost: UNPLAYABLE,
       upgraded_cost: -3,
       ethereal: false,
+      retain: false,
+      strike: false,
       has_target: false,
       exhausts: false,
+      upgraded_exhausts: None,
+      class: CardClass::Red,
     }
   }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(PartialEq, Eq, Hash, Serialize, Deserialize, Derivative)]
+#[derivative(Clone(clone_from = "true"))]
 pub struct Creature {
   pub hitpoints: i32,
   pub max_hitpoints: i32,
@@ -137,13 +199,89 @@ pub struct Creature {
   pub powers: Vec<Power>,
 }
 
-#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+#[derive(PartialEq, Eq, Hash, Serialize, Deserialize, Debug, Derivative)]
+#[derivative(Clone(clone_from = "true"))]
 pub struct Player {
   pub creature: Creature,
   pub energy: i32,
+  #[serde(default)]
+  pub stance: Stance,
+  /// Channeled orbs, oldest first; the oldest is the next one evoked.
+  #[serde(default)]
+  pub orbs: Vec<Orb>,
+  #[serde(default)]
+  pub orb_slots: i32,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, Debug)]
+pub enum OrbType {
+  Lightning,
+  Frost,
+  Dark,
+  Plasma,
 }
 
 #[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub struct Orb {
+  pub orb_type: OrbType,
+  /// For Dark, the damage its evoke will deal, which grows with each passive trigger.
+  pub misc: i32,
+}
+
+impl Orb {
+  pub fn new(orb_type: OrbType) -> Orb {
+    Orb {
+      orb_type,
+      misc: if orb_type == OrbType::Dark { 6 } else { 0 },
+    }
+  }
+
+  /// Empty slots are reported as orbs too; they give `None`.
+  fn from_communication_mod(orb: &communication::Orb) -> Option<Orb> {
+    let orb_type = match &*orb.id {
+      "Lightning" => OrbType::Lightning,
+      "Frost" => OrbType::Frost,
+      "Dark" => OrbType::Dark,
+      "Plasma" => OrbType::Plasma,
+      _ => return None,
+    };
+    Some(Orb {
+      orb_type,
+      misc: if orb_type == OrbType::Dark { orb.evoke_amount } else { 0 },
+    })
+  }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, Debug, Derivative)]
+#[derivative(Default)]
+pub enum Stance {
+  #[derivative(Default)]
+  Neutral,
+  Calm,
+  Wrath,
+  Divinity,
+}
+
+impl Stance {
+  /// Multiplies Normal damage dealt by the player.
+  pub fn damage_give_multiplier(self) -> f64 {
+    match self {
+      Stance::Wrath => 2.0,
+      Stance::Divinity => 3.0,
+      _ => 1.0,
+    }
+  }
+  /// Multiplies Normal damage dealt to the player.
+  pub fn damage_receive_multiplier(self) -> f64 {
+    match self {
+      Stance::Wrath => 2.0,
+      _ => 1.0,
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, Debug, Derivative)]
+#[derivative(Clone(clone_from = "true"), PartialEq, Eq, Hash)]
 pub struct Monster {
   pub monster_id: MonsterId,
   pub innate_damage_amount: Option<i32>,
@@ -152,6 +290,11 @@ pub struct Monster {
   pub creature: Creature,
   pub move_history: Vec<i32>,
   pub gone: bool,
+  /// The classified current intent, refreshed by `CombatState::update_intent_caches` once the state settles
+  /// if anything it depends on changed. `CombatState::intent_kind` ignores it if it's out of date.
+  #[serde(skip)]
+  #[derivative(PartialEq = "ignore", Hash = "ignore")]
+  pub intent_cache: Option<monsters::IntentCache>,
 }
 
 #[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -179,12 +322,26 @@ impl Default for Power {
   }
 }
 
-/*#[derive(Clone, Serialize, Deserialize, Debug)]
+/// A relic the player had when the state was read from the game. The relics the simulation knows are
+/// also imported as powers of the player with `PowerType::Relic` (see `From<&communication::Relic> for Power`),
+/// with the counter as the amount, so their hooks fire along with the powers'; this list keeps every relic,
+/// including the ones the simulation doesn't know.
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
 pub struct Relic {
-  name: String,
-  id: String,
-  counter: i32,
-}*/
+  pub name: String,
+  pub id: String,
+  pub counter: i32,
+}
+
+impl From<&communication::Relic> for Relic {
+  fn from(relic: &communication::Relic) -> Relic {
+    Relic {
+      name: relic.name.clone(),
+      id: relic.id.clone(),
+      counter: relic.counter,
+    }
+  }
+}
 
 impl CombatState {
   pub fn from_communication_mod(
@@ -194,8 +351,14 @@ impl CombatState {
   
     let combat = observed.combat_state.as_ref()?;
     let mut draw_pile: Vec<SingleCard> = combat.draw_pile.iter().map(From::from).collect();
-    // explicitly sort, partly to make sure my AI doesn't accidentally cheat
-    draw_pile.sort();
+    // Frozen Eye shows the player the order of the draw pile (whose top is the end of the list, like ours),
+    // otherwise explicitly sort, partly to make sure my AI doesn't accidentally cheat
+    let draw_pile_known_top = if observed.relics.iter().any(|relic| relic.id == "Frozen Eye") {
+      draw_pile.len()
+    } else {
+      draw_pile.sort();
+      0
+    };
     
     let mut result = CombatState {
       draw_pile,
@@ -210,6 +373,14 @@ impl CombatState {
       player: Player::from_communication_mod(&combat.player, & observed.relics),
       turn_number: combat.turn,
       turn_has_ended: false,
+      cached_combat_over: false,
+      draw_pile_known_top,
+      next_turn_draw_modifier: 0,
+      delayed_actions: Vec::new(),
+      repeat_next: Vec::new(),
+      starting_gold: observed.gold,
+      gold_gained: 0,
+      relics: observed.relics.iter().map(From::from).collect(),
       monsters: combat
         .monsters
         .iter()
@@ -238,6 +409,7 @@ impl CombatState {
               powers: monster.powers.iter().map(From::from).collect(),
             },
             gone: monster.is_gone,
+            intent_cache: None,
           }
         })
         .collect(),
@@ -257,15 +429,45 @@ impl CombatState {
           }*/
       }
     }
+    // the simulation tracks these as `repeat_next` instead of as powers
+    for power in &result.player.creature.powers {
+      if let Some(card_type) = repeated_card_type(power.power_id) {
+        result.add_repeat_next(card_type, power.amount as u32);
+      }
+    }
+    result
+      .player
+      .creature
+      .powers
+      .retain(|power| repeated_card_type(power.power_id).is_none());
+
+    // the relics come first, so re-sort to get the priority order that the hooks rely on;
+    // the sort is stable, so relics still go first among equal priorities, like the game's relic hooks
+    result.player.creature.powers.sort_by_key(|power| power.power_id.priority());
+    for monster in &mut result.monsters {
+      monster.creature.powers.sort_by_key(|power| power.power_id.priority());
+    }
+
+    result.update_combat_over();
     Some(result)
   }
 }
 
+fn repeated_card_type(power_id: PowerId) -> Option<CardType> {
+  match power_id {
+    PowerId::DoubleTap => Some(CardType::Attack),
+    PowerId::Burst => Some(CardType::Skill),
+    PowerId::Amplify => Some(CardType::Power),
+    _ => None,
+  }
+}
+
 impl From<&communication::Card> for SingleCard {
   fn from(card: &communication::Card) -> SingleCard {
     SingleCard {
       misc: card.misc,
       cost: card.cost,
+      cost_for_turn: None,
       upgrades: card.upgrades,
       card_info: Arc::new(CardInfo::from(CardId::from(&*card.id))),
     }
@@ -287,8 +489,12 @@ impl From<&communication::Power> for Power {
 
 impl From<&communication::Relic> for Power {
   fn from(relic: &communication::Relic) -> Power {
+    let power_id = match &*relic.id {
+      "Pen Nib" => PowerId::PenNibRelic,
+      id => PowerId::from(id),
+    };
     Power {
-      power_id: PowerId::from(&*relic.id),
+      power_id,
       amount: relic.counter,
       damage: 0,
       card: None,
@@ -302,6 +508,10 @@ impl Player {
   fn from_communication_mod(player: &communication::Player, relics: & [communication::Relic]) -> Player {
     Player {
       energy: player.energy,
+      // CommunicationMod doesn't report the stance
+      stance: Stance::Neutral,
+      orbs: player.orbs.iter().filter_map(Orb::from_communication_mod).collect(),
+      orb_slots: player.orbs.len() as i32,
       creature: Creature {
         hitpoints: player.current_hp,
         max_hitpoints: player.max_hp,
@@ -326,13 +536,45 @@ impl SingleCard {
     SingleCard {
       misc: 0,
       cost: info.normal_cost,
+      cost_for_turn: None,
       upgrades: 0,
       card_info: Arc::new(info),
     }
   }
   
+  pub fn exhausts(&self) -> bool {
+    match self.card_info.upgraded_exhausts {
+      Some(exhausts) if self.upgrades > 0 => exhausts,
+      _ => self.card_info.exhausts,
+    }
+  }
+
+  /// The `Debug` output followed by what the card would cost to play right now, e.g. "Bash+ [2]",
+  /// taking effects like Corruption into account. X costs show as "X", unplayable cards as "-".
+  pub fn display_in(&self, state: &CombatState) -> String {
+    let cost = match state.effective_cost(self) {
+      X_COST => "X".to_string(),
+      UNPLAYABLE => "-".to_string(),
+      cost => cost.to_string(),
+    };
+    format!("{:?} [{}]", self, cost)
+  }
+
+  /// Upgrades the card if it isn't already; curses and statuses can't be upgraded. A card whose upgrade
+  /// is cheaper has its cost lowered by the difference, so cost changes made during combat are kept.
   pub fn upgrade (&mut self) {
-    if self.upgrades == 0 {self.upgrades = 1;}
+    let card_type = self.card_info.card_type;
+    if self.upgrades > 0 || card_type == CardType::Curse || card_type == CardType::Status {
+      return;
+    }
+    self.upgrades = 1;
+    let cost_change = self.card_info.upgraded_cost - self.card_info.normal_cost;
+    if cost_change != 0 && self.cost >= 0 {
+      self.cost = std::cmp::max(0, self.cost + cost_change);
+      self.cost_for_turn = self
+        .cost_for_turn
+        .map(|cost| std::cmp::max(0, cost + cost_change));
+    }
   }
 }
 
@@ -397,3 +639,149 @@ impl Debug for SingleCard {
     }
   }
 }*/
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::simulation_state::testing::*;
+
+  fn game_state(relics: serde_json::Value) -> communication::GameState {
+    serde_json::from_value(serde_json::json!({
+      "screen_name": "NONE", "is_screen_up": false, "screen_type": "NONE", "screen_state": {},
+      "room_phase": "COMBAT", "action_phase": "WAITING_ON_USER", "room_type": "MonsterRoom",
+      "current_hp": 70, "max_hp": 80, "floor": 1, "act": 1, "act_boss": "Hexaghost", "gold": 99,
+      "seed": 0, "class": "IRONCLAD", "ascension_level": 0, "relics": relics, "deck": [],
+      "potions": [], "map": [], "current_action": null, "choice_list": null,
+      "combat_state": {
+        "draw_pile": [], "discard_pile": [], "exhaust_pile": [], "hand": [], "limbo": [],
+        "card_in_play": null, "cards_discarded_this_turn": 0, "turn": 1,
+        "player": {"current_hp": 70, "max_hp": 80, "block": 0, "powers": [], "energy": 3, "orbs": []},
+        "monsters": [{
+          "name": "Cultist", "id": "Cultist", "current_hp": 50, "max_hp": 50, "block": 0,
+          "intent": "BUFF", "move_id": 3, "last_move_id": null, "second_last_move_id": null,
+          "half_dead": false, "is_gone": false, "powers": []
+        }]
+      }
+    }))
+    .unwrap()
+  }
+
+  #[test]
+  fn relics_are_read_from_the_game_state() {
+    let observed = game_state(serde_json::json!([
+      {"name": "Pen Nib", "id": "Pen Nib", "counter": 7},
+      {"name": "Akabeko", "id": "Akabeko", "counter": -1},
+    ]));
+    let state = CombatState::from_communication_mod(&observed, None).unwrap();
+    assert_eq!(
+      state.relics,
+      vec![
+        Relic {
+          name: "Pen Nib".to_string(),
+          id: "Pen Nib".to_string(),
+          counter: 7
+        },
+        Relic {
+          name: "Akabeko".to_string(),
+          id: "Akabeko".to_string(),
+          counter: -1
+        },
+      ]
+    );
+    assert_eq!(state.player.creature.power_amount(PowerId::PenNibRelic), 7);
+  }
+
+  #[test]
+  fn upgrading_applies_the_change_in_base_cost() {
+    let mut body_slam = SingleCard::create(CardId::BodySlam);
+    body_slam.upgrade();
+    assert_eq!((body_slam.upgrades, body_slam.cost), (1, 0));
+
+    let mut blood_for_blood = SingleCard::create(CardId::BloodForBlood);
+    blood_for_blood.cost = 2;
+    blood_for_blood.cost_for_turn = Some(1);
+    blood_for_blood.upgrade();
+    assert_eq!(blood_for_blood.cost, 1);
+    assert_eq!(blood_for_blood.cost_for_turn, Some(0));
+
+    let mut strike = SingleCard::create(CardId::StrikeR);
+    strike.upgrade();
+    strike.upgrade();
+    assert_eq!((strike.upgrades, strike.cost), (1, 1));
+  }
+
+  #[test]
+  fn curses_and_statuses_are_not_upgraded() {
+    for &id in &[CardId::Injury, CardId::Wound, CardId::Dazed] {
+      let mut card = SingleCard::create(id);
+      card.upgrade();
+      assert_eq!(card, SingleCard::create(id));
+    }
+  }
+
+  #[test]
+  fn a_combat_without_relics_has_none() {
+    let state = CombatState::from_communication_mod(&game_state(serde_json::json!([])), None).unwrap();
+    assert!(state.relics.is_empty());
+    assert_eq!(state.player.relics().count(), 0);
+    assert!(!state.combat_over());
+  }
+
+  #[test]
+  fn clone_from_reuses_the_piles_allocations() {
+    let mut state = combat(vec![monster(MonsterId::Cultist, 50, vec![1])], Vec::new());
+    state.draw_pile = (0..10).map(|_| card(CardId::StrikeR)).collect();
+    let mut scratch = state.clone();
+    scratch.draw_pile.reserve(100);
+    scratch.discard_pile.extend(state.draw_pile.drain(..5));
+    let draw_pile = (scratch.draw_pile.as_ptr(), scratch.draw_pile.capacity());
+    let discard_pile = (scratch.discard_pile.as_ptr(), scratch.discard_pile.capacity());
+
+    scratch.clone_from(&state);
+    assert_eq!(scratch, state);
+    assert_eq!((scratch.draw_pile.as_ptr(), scratch.draw_pile.capacity()), draw_pile);
+    assert_eq!(scratch.discard_pile.as_ptr(), discard_pile.0);
+    assert!(scratch.discard_pile.is_empty());
+  }
+
+  #[test]
+  fn the_draw_order_is_only_known_with_frozen_eye() {
+    let with_draw_pile = |relics| {
+      let mut observed = game_state(relics);
+      let card = |id: &str, cost| {
+        serde_json::from_value::<communication::Card>(serde_json::json!({
+          "name": id, "uuid": id, "cost": cost, "upgrades": 0, "id": id, "type": "ATTACK",
+          "rarity": "BASIC", "has_target": true, "exhausts": false,
+        }))
+        .unwrap()
+      };
+      observed.combat_state.as_mut().unwrap().draw_pile = vec![card("Strike_R", 1), card("Bash", 2)];
+      CombatState::from_communication_mod(&observed, None).unwrap()
+    };
+    let unknown = with_draw_pile(serde_json::json!([]));
+    assert_eq!(unknown.draw_pile_known_top, 0);
+    assert!(matches!(DrawCardRandom.determinism(&unknown), Determinism::Random(_)));
+
+    let known = with_draw_pile(serde_json::json!([{"name": "Frozen Eye", "id": "Frozen Eye", "counter": -1}]));
+    assert_eq!(known.draw_pile_known_top, 2);
+    assert_eq!(DrawCardRandom.determinism(&known), Determinism::Deterministic);
+    assert_eq!(known.draw_pile.last().unwrap().card_info.id, CardId::Bash);
+  }
+
+  #[test]
+  fn display_in_shows_the_cost_to_play_the_card_now() {
+    let mut state = combat(vec![monster(MonsterId::Cultist, 50, vec![3])], Vec::new());
+    let mut bash = card(CardId::Bash);
+    assert!(bash.display_in(&state).ends_with(" [2]"));
+    bash.cost_for_turn = Some(0);
+    assert!(bash.display_in(&state).ends_with(" [0]"));
+
+    let defend = card(CardId::DefendR);
+    assert!(defend.display_in(&state).ends_with(" [1]"));
+    state.player.creature.powers.push(power(PowerId::Corruption, -1));
+    assert!(defend.display_in(&state).ends_with(" [0]"));
+
+    assert!(card(CardId::Whirlwind).display_in(&state).ends_with(" [X]"));
+    assert!(card(CardId::Parasite).display_in(&state).ends_with(" [-]"));
+  }
+}
@@ -19,6 +19,9 @@ pub trait CardBehavior: Sized + Copy + Into<CardId> {
   fn playable(self, state: &CombatState) -> bool {
     true
   }
+  /// Called for each card in the discard pile when the player changes stance.
+  #[allow(unused)]
+  fn on_stance_change(self, runner: &mut Runner, card: &SingleCard) {}
 }
 
 pub trait CardBehaviorContext {
@@ -27,14 +30,23 @@ pub trait CardBehaviorContext {
   fn target_creature_index(&self) -> CreatureIndex {
     CreatureIndex::Monster(self.target())
   }
-  fn attack_target(&mut self, base_damage: i32) {
+  fn target_attack_info(&self, base_damage: i32) -> DamageInfo {
     // hack: this is actually NOT where powers are applied to card/monster damage in the actual code
-    let mut info = DamageInfo::new(CreatureIndex::Player, base_damage, DamageType::Normal);
+    let mut info = DamageInfo::new(CreatureIndex::Player, base_damage, DamageType::Normal)
+      .with_source(DamageSource::Card(self.card().card_info.id));
     info.apply_powers(
       self.state(),
       CreatureIndex::Player,
       self.target_creature_index(),
+      Some(self.card()),
     );
+    info
+  }
+  /// Multi-hit attacks call this once per hit. Each hit is its own `DamageAction`, so block and
+  /// `on_attacked` reactions like Flame Barrier are handled hit by hit, but as in the game, the damage
+  /// of every hit is calculated when the card is played.
+  fn attack_target(&mut self, base_damage: i32) {
+    let info = self.target_attack_info(base_damage);
     self.action(DamageAction {
       target: self.target_creature_index(),
       info,
@@ -47,6 +59,7 @@ pub trait CardBehaviorContext {
       self.state(),
       CreatureIndex::Player,
       self.target_creature_index(),
+      Some(self.card()),
     );
     self.action(DamageAllEnemiesAction {
       damage: info.output,
@@ -91,10 +104,21 @@ pub trait CardBehaviorContext {
       amount: amount as i32,
     });
   }
+  fn lose_hitpoints(&mut self, amount: i32) {
+    self.action(DamageAction {
+      target: CreatureIndex::Player,
+      info: DamageInfo::new(CreatureIndex::Player, amount, DamageType::HitpointLoss)
+        .with_source(DamageSource::Card(self.card().card_info.id)),
+    });
+  }
   fn draw_cards(&mut self, amount: i32) {
     self.action(DrawCards(amount));
   }
   fn state(&self) -> &CombatState;
+  /// For X-cost cards, the energy that was spent on the card.
+  fn x_value(&self) -> i32 {
+    self.state().player.energy
+  }
   fn card(&self) -> &SingleCard {
     self.state().card_in_play.as_ref().unwrap()
   }
@@ -113,6 +137,7 @@ pub trait CardBehaviorContext {
 pub struct PlayCardContext<'a, 'b> {
   pub runner: &'a mut Runner<'b>,
   pub target: usize,
+  pub x_value: i32,
 }
 
 
@@ -126,6 +151,9 @@ impl<'a, 'b> CardBehaviorContext for PlayCardContext<'a, 'b> {
   fn state(&self) -> &CombatState {
     self.runner.state()
   }
+  fn x_value(&self) -> i32 {
+    self.x_value
+  }
 }
 
 pub struct ConsiderCardContext<'a> {
@@ -150,6 +178,17 @@ impl<'a> CardBehaviorContext for ConsiderCardContext<'a> {
   }
 }
 
+/// The cards that "random card" effects in combat can generate with the given type.
+/// Colorless and special cards are excluded, like in the game.
+pub fn combat_card_pool(class: CardClass, card_type: CardType) -> impl Iterator<Item = CardId> {
+  ALL_CARD_IDS.iter().cloned().filter(move |&id| {
+    let info = CardInfo::from(id);
+    info.class == class
+      && info.card_type == card_type
+      && (info.rarity == Common || info.rarity == Uncommon || info.rarity == Rare)
+  })
+}
+
 pub fn card_actions (state: &CombatState, card: SingleCard, target: usize) -> SmallVec<[DynAction; 4]> {
   let mut context = ConsiderCardContext {state, target, card: card.clone(), actions: SmallVec::new(),};
   card.card_info.id.behavior (&mut context);
@@ -182,6 +221,8 @@ macro_rules! cards {
       }
     }
 
+    pub const ALL_CARD_IDS: &[CardId] = &[$(CardId::$Variant,)*];
+
     impl From <CardId> for CardInfo {
       fn from (source: CardId)->CardInfo {
         match source {
@@ -213,6 +254,11 @@ macro_rules! cards {
           $(CardId::$Variant => $Variant.playable(state),)*
         }
       }
+      fn on_stance_change(self, runner: &mut Runner, card: &SingleCard) {
+        match self {
+          $(CardId::$Variant => $Variant.on_stance_change(runner, card),)*
+        }
+      }
     }
   }
 }
@@ -221,7 +267,7 @@ pub const HAS_TARGET: bool = true;
 pub const NO_TARGET: bool = false;
 
 cards! {
-  ["Strike_R", StrikeR, Attack, Basic, 1, HAS_TARGET, {}],
+  ["Strike_R", StrikeR, Attack, Basic, 1, HAS_TARGET, {strike: true,}],
   ["Bash", Bash, Attack, Basic, 2, HAS_TARGET, {}],
   ["Defend_R", DefendR, Skill, Basic, 1, NO_TARGET, {}],
 
@@ -236,15 +282,15 @@ cards! {
   ["Headbutt", Headbutt, Attack, Common, 1, HAS_TARGET, {}],
   ["Heavy Blade", HeavyBlade, Attack, Common, 2, HAS_TARGET, {}],
   ["Iron Wave", IronWave, Attack, Common, 1, HAS_TARGET, {}],
-  ["Perfected Strike", PerfectedStrike, Attack, Common, 2, HAS_TARGET, {}],
-  ["Pommel Strike", PommelStrike, Attack, Common, 1, HAS_TARGET, {}],
+  ["Perfected Strike", PerfectedStrike, Attack, Common, 2, HAS_TARGET, {strike: true,}],
+  ["Pommel Strike", PommelStrike, Attack, Common, 1, HAS_TARGET, {strike: true,}],
   ["Shrug It Off", ShrugItOff, Skill, Common, 1, NO_TARGET, {}],
   ["Sword Boomerang", SwordBoomerang, Attack, Common, 1, NO_TARGET, {}],
   ["Thunderclap", Thunderclap, Attack, Common, 1, NO_TARGET, {}],
   ["True Grit", TrueGrit, Skill, Common, 1, NO_TARGET, {}],
-  ["Twin Strike", TwinStrike, Attack, Common, 1, HAS_TARGET, {}],
-  ["Warcry", Warcry, Skill, Common, 0, NO_TARGET, {}],
-  ["Wild Strike", WildStrike, Attack, Common, 1, HAS_TARGET, {}],
+  ["Twin Strike", TwinStrike, Attack, Common, 1, HAS_TARGET, {strike: true,}],
+  ["Warcry", Warcry, Skill, Common, 0, NO_TARGET, {exhausts: true,}],
+  ["Wild Strike", WildStrike, Attack, Common, 1, HAS_TARGET, {strike: true,}],
 
   ["Battle Trance", BattleTrance, Skill, Uncommon, 0, NO_TARGET, {}],
   ["Blood for Blood", BloodForBlood, Attack, Uncommon, 4, HAS_TARGET, {upgraded_cost: 3,}],
@@ -263,7 +309,7 @@ cards! {
   ["Flame Barrier", FlameBarrier, Skill, Uncommon, 2, NO_TARGET, {}],
   ["Ghostly Armor", GhostlyArmor, Skill, Uncommon, 1, NO_TARGET, {ethereal: true,}],
   ["Hemokinesis", Hemokinesis, Attack, Uncommon, 1, HAS_TARGET, {}],
-  ["Infernal Blade", InfernalBlade, Skill, Uncommon, 1, NO_TARGET, {}],
+  ["Infernal Blade", InfernalBlade, Skill, Uncommon, 1, NO_TARGET, {exhausts: true, upgraded_cost: 0,}],
   ["Inflame", Inflame, Power, Uncommon, 1, NO_TARGET, {}],
   ["Intimidate", Intimidate, Skill, Uncommon, 0, NO_TARGET, {exhausts: true,}],
   ["Metallicize", Metallicize, Power, Uncommon, 1, NO_TARGET, {}],
@@ -281,7 +327,7 @@ cards! {
   ["Shockwave", Shockwave, Skill, Uncommon, 2, NO_TARGET, {}],
   ["Spot Weakness", SpotWeakness, Skill, Uncommon, 1, HAS_TARGET, {}],
   ["Uppercut", Uppercut, Attack, Uncommon, 2, HAS_TARGET, {}],
-  ["Whirlwind", Whirlwind, Attack, Uncommon, X_COST, HAS_TARGET, {}],
+  ["Whirlwind", Whirlwind, Attack, Uncommon, X_COST, NO_TARGET, {}],
   
   ["Barricade", Barricade, Power, Rare, 3, NO_TARGET, {upgraded_cost: 2,}],
   ["Berserk", Berserk, Power, Rare, 0, NO_TARGET, {}],
@@ -294,11 +340,52 @@ cards! {
   ["Impervious", Impervious, Skill, Rare, 2, NO_TARGET, {exhausts: true,}],
   ["Juggernaut", Juggernaut, Power, Rare, 2, NO_TARGET, {}],
   
-  ["Injury", Injury, Curse, Special, UNPLAYABLE, NO_TARGET, {}],
-  ["AscendersBane", AscendersBane, Curse, Special, UNPLAYABLE, NO_TARGET, {ethereal: true,}],
-  ["Dazed", Dazed, Status, Special, UNPLAYABLE, NO_TARGET, {ethereal: true,}],
-  ["Slimed", Slimed, Status, Special, 1, NO_TARGET, {exhausts: true,}],
-  ["Burn", Burn, Status, Special, UNPLAYABLE, NO_TARGET, {}],
+  ["Tools of the Trade", ToolsOfTheTrade, Power, Rare, 1, NO_TARGET, {class: CardClass::Green, upgraded_cost: 0,}],
+  ["Sadistic Nature", SadisticNature, Power, Rare, 0, NO_TARGET, {class: CardClass::Green,}],
+  ["Envenom", Envenom, Power, Rare, 2, NO_TARGET, {class: CardClass::Green, upgraded_cost: 1,}],
+  ["Noxious Fumes", NoxiousFumes, Power, Uncommon, 1, NO_TARGET, {class: CardClass::Green,}],
+  ["Burst", Burst, Skill, Rare, 1, NO_TARGET, {class: CardClass::Green,}],
+  ["Accuracy", Accuracy, Power, Uncommon, 1, NO_TARGET, {class: CardClass::Green,}],
+  ["Blade Dance", BladeDance, Skill, Common, 1, NO_TARGET, {class: CardClass::Green,}],
+  ["Acrobatics", Acrobatics, Skill, Common, 1, NO_TARGET, {class: CardClass::Green,}],
+  ["Calculated Gamble", CalculatedGamble, Skill, Uncommon, 0, NO_TARGET, {class: CardClass::Green, exhausts: true, upgraded_exhausts: Some(false),}],
+  ["Cloak And Dagger", CloakAndDagger, Skill, Common, 1, NO_TARGET, {class: CardClass::Green,}],
+  ["Expertise", Expertise, Skill, Uncommon, 1, NO_TARGET, {class: CardClass::Green,}],
+  ["Setup", Setup, Skill, Uncommon, 1, NO_TARGET, {class: CardClass::Green, upgraded_cost: 0,}],
+  ["Skewer", Skewer, Attack, Uncommon, X_COST, HAS_TARGET, {class: CardClass::Green,}],
+  
+  ["Zap", Zap, Skill, Basic, 1, NO_TARGET, {class: CardClass::Blue, upgraded_cost: 0,}],
+  ["Ball Lightning", BallLightning, Attack, Common, 1, HAS_TARGET, {class: CardClass::Blue,}],
+  ["Rebound", Rebound, Attack, Common, 1, HAS_TARGET, {class: CardClass::Blue,}],
+  ["Coolheaded", Coolheaded, Skill, Common, 1, NO_TARGET, {class: CardClass::Blue,}],
+  ["Darkness", Darkness, Skill, Uncommon, 1, NO_TARGET, {class: CardClass::Blue,}],
+  ["Fusion", Fusion, Skill, Uncommon, 2, NO_TARGET, {class: CardClass::Blue, upgraded_cost: 1,}],
+  ["Defragment", Defragment, Power, Uncommon, 1, NO_TARGET, {class: CardClass::Blue,}],
+  ["Amplify", Amplify, Skill, Uncommon, 1, NO_TARGET, {class: CardClass::Blue,}],
+  
+  ["Eruption", Eruption, Attack, Basic, 2, HAS_TARGET, {class: CardClass::Purple, upgraded_cost: 1,}],
+  ["Vigilance", Vigilance, Skill, Basic, 2, NO_TARGET, {class: CardClass::Purple,}],
+  ["FlurryOfBlows", FlurryOfBlows, Attack, Common, 0, HAS_TARGET, {class: CardClass::Purple,}],
+  ["FlyingSleeves", FlyingSleeves, Attack, Common, 1, HAS_TARGET, {class: CardClass::Purple, retain: true,}],
+  ["Protect", Protect, Skill, Common, 2, NO_TARGET, {class: CardClass::Purple, retain: true,}],
+  ["Establishment", Establishment, Power, Rare, 1, NO_TARGET, {class: CardClass::Purple,}],
+  ["Wreath of Flame", WreathOfFlame, Skill, Uncommon, 1, NO_TARGET, {class: CardClass::Purple,}],
+  
+  ["Panache", Panache, Power, Uncommon, 0, NO_TARGET, {class: CardClass::Colorless,}],
+  ["Deep Breath", DeepBreath, Skill, Uncommon, 0, NO_TARGET, {class: CardClass::Colorless,}],
+  ["Finesse", Finesse, Skill, Uncommon, 0, NO_TARGET, {class: CardClass::Colorless,}],
+  ["Apotheosis", Apotheosis, Skill, Rare, 2, NO_TARGET, {class: CardClass::Colorless, exhausts: true, upgraded_cost: 1,}],
+  ["The Bomb", TheBomb, Skill, Rare, 2, NO_TARGET, {class: CardClass::Colorless,}],
+  ["Hand of Greed", HandOfGreed, Attack, Rare, 2, HAS_TARGET, {class: CardClass::Colorless,}],
+  ["Shiv", Shiv, Attack, Special, 0, HAS_TARGET, {class: CardClass::Colorless, exhausts: true,}],
+  
+  ["Injury", Injury, Curse, Special, UNPLAYABLE, NO_TARGET, {class: CardClass::Curse,}],
+  ["AscendersBane", AscendersBane, Curse, Special, UNPLAYABLE, NO_TARGET, {class: CardClass::Curse, ethereal: true,}],
+  ["Parasite", Parasite, Curse, Special, UNPLAYABLE, NO_TARGET, {class: CardClass::Curse,}],
+  ["Dazed", Dazed, Status, Special, UNPLAYABLE, NO_TARGET, {class: CardClass::Colorless, ethereal: true,}],
+  ["Slimed", Slimed, Status, Special, 1, NO_TARGET, {class: CardClass::Colorless, exhausts: true,}],
+  ["Burn", Burn, Status, Special, UNPLAYABLE, NO_TARGET, {class: CardClass::Colorless,}],
+  ["Wound", Wound, Status, Special, UNPLAYABLE, NO_TARGET, {class: CardClass::Colorless,}],
 }
 
 impl CardBehavior for StrikeR {
@@ -381,7 +468,7 @@ impl CardBehavior for Havoc {
 impl CardBehavior for Headbutt {
   fn behavior(self, context: &mut impl CardBehaviorContext) {
     context.attack_target(context.with_upgrade(12, 9));
-    //TODO: retrieve card
+    context.action(ChooseCardsAction::new(CardChoiceType::DiscardTopdeck, 1));
   }
 }
 
@@ -440,7 +527,11 @@ impl CardBehavior for Thunderclap {
 impl CardBehavior for TrueGrit {
   fn behavior(self, context: &mut impl CardBehaviorContext) {
     context.block(context.with_upgrade(9, 7));
-    //TODO: exhaust
+    if context.upgraded() {
+      context.action(ChooseCardsAction::new(CardChoiceType::ExhaustCard, 1));
+    } else {
+      context.action(ExhaustRandomCardAction);
+    }
   }
 }
 
@@ -454,7 +545,8 @@ impl CardBehavior for TwinStrike {
 
 impl CardBehavior for Warcry {
   fn behavior(self, context: &mut impl CardBehaviorContext) {
-    //TODO
+    context.draw_cards(context.with_upgrade(2, 1));
+    context.action(ChooseCardsAction::new(CardChoiceType::HandTopdeck, 1));
   }
 }
 
@@ -481,7 +573,9 @@ impl CardBehavior for BloodForBlood {
 
 impl CardBehavior for Bloodletting {
   fn behavior(self, context: &mut impl CardBehaviorContext) {
-    //TODO
+    // like in the game, this doesn't exhaust, and the HP loss can kill the player
+    context.lose_hitpoints(3);
+    context.action(GainEnergyAction(context.with_upgrade(3, 2)));
   }
 }
 
@@ -524,7 +618,8 @@ impl CardBehavior for Dropkick {
 
 impl CardBehavior for DualWield {
   fn behavior(self, context: &mut impl CardBehaviorContext) {
-    //TODO
+    let copies = context.with_upgrade(2, 1);
+    context.action(ChooseCardsAction::new(CardChoiceType::Duplicate(copies), 1));
   }
 }
 
@@ -567,14 +662,18 @@ impl CardBehavior for GhostlyArmor {
 
 impl CardBehavior for Hemokinesis {
   fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.lose_hitpoints(2);
     context.attack_target(context.with_upgrade(18, 14));
-    // TODO other effect
   }
 }
 
 impl CardBehavior for InfernalBlade {
   fn behavior(self, context: &mut impl CardBehaviorContext) {
-    //TODO
+    context.action(HandRandomCardAction {
+      class: context.card().card_info.class,
+      card_type: Attack,
+      cost_for_turn: Some(0),
+    });
   }
 }
 
@@ -633,7 +732,7 @@ impl CardBehavior for RecklessCharge {
 
 impl CardBehavior for Rupture {
   fn behavior(self, context: &mut impl CardBehaviorContext) {
-    //TODO
+    context.power_self(PowerId::Rupture, context.with_upgrade(2, 1));
   }
 }
 
@@ -695,7 +794,9 @@ impl CardBehavior for Uppercut {
 
 impl CardBehavior for Whirlwind {
   fn behavior(self, context: &mut impl CardBehaviorContext) {
-    //TODO
+    for _ in 0..context.x_value() {
+      context.attack_monsters(context.with_upgrade(8, 5));
+    }
   }
 }
 
@@ -738,7 +839,10 @@ impl CardBehavior for DemonForm {
 
 impl CardBehavior for DoubleTap {
   fn behavior(self, context: &mut impl CardBehaviorContext) {
-    context.power_self(PowerId::DoubleTap, context.with_upgrade(2, 1));
+    context.action(RepeatNextCardsAction {
+      card_type: Attack,
+      count: context.with_upgrade(2, 1),
+    });
   }
 }
 
@@ -761,9 +865,657 @@ impl CardBehavior for Juggernaut {
   }
 }
 
+impl CardBehavior for ToolsOfTheTrade {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.power_self(PowerId::ToolsOfTheTrade, 1);
+  }
+}
+
+impl CardBehavior for SadisticNature {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.power_self(PowerId::SadisticNature, context.with_upgrade(7, 5));
+  }
+}
+
+impl CardBehavior for Envenom {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.power_self(PowerId::Envenom, 1);
+  }
+}
+
+impl CardBehavior for NoxiousFumes {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.power_self(PowerId::NoxiousFumes, context.with_upgrade(3, 2));
+  }
+}
+
+impl CardBehavior for Burst {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.action(RepeatNextCardsAction {
+      card_type: Skill,
+      count: context.with_upgrade(2, 1),
+    });
+  }
+}
+
+impl CardBehavior for Accuracy {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.power_self(PowerId::Accuracy, context.with_upgrade(6, 4));
+  }
+}
+
+impl CardBehavior for BladeDance {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    for _ in 0..context.with_upgrade(4, 3) {
+      context.action(HandNewCard(SingleCard::create(CardId::Shiv)));
+    }
+  }
+}
+
+impl CardBehavior for CloakAndDagger {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.block(6);
+    for _ in 0..context.with_upgrade(2, 1) {
+      context.action(HandNewCard(SingleCard::create(CardId::Shiv)));
+    }
+  }
+}
+
+impl CardBehavior for Acrobatics {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.draw_cards(context.with_upgrade(4, 3));
+    context.action(ChooseCardsAction::new(CardChoiceType::DiscardCard, 1));
+  }
+}
+
+impl CardBehavior for CalculatedGamble {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.action(CalculatedGambleAction);
+  }
+}
+
+impl CardBehavior for Expertise {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.action(DrawToHandSize(context.with_upgrade(7, 6)));
+  }
+}
+
+impl CardBehavior for Setup {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.action(ChooseCardsAction::new(CardChoiceType::SetupTopdeck, 1));
+  }
+}
+
+impl CardBehavior for Skewer {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    for _ in 0..context.x_value() {
+      context.attack_target(context.with_upgrade(10, 7));
+    }
+  }
+}
+
+impl CardBehavior for Zap {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.action(ChannelOrb(OrbType::Lightning));
+  }
+}
+
+impl CardBehavior for BallLightning {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.attack_target(context.with_upgrade(10, 7));
+    context.action(ChannelOrb(OrbType::Lightning));
+  }
+}
+
+// Rebound itself has already gone to the discard pile by the time the power is applied,
+// so it doesn't rebound itself
+impl CardBehavior for Rebound {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.attack_target(context.with_upgrade(12, 9));
+    context.power_self(PowerId::Rebound, 1);
+  }
+}
+
+impl CardBehavior for Coolheaded {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.action(ChannelOrb(OrbType::Frost));
+    context.draw_cards(context.with_upgrade(2, 1));
+  }
+}
+
+impl CardBehavior for Darkness {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.action(ChannelOrb(OrbType::Dark));
+    if context.upgraded() {
+      context.action(DarkImpulse);
+    }
+  }
+}
+
+impl CardBehavior for Fusion {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.action(ChannelOrb(OrbType::Plasma));
+  }
+}
+
+impl CardBehavior for Defragment {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.power_self(PowerId::Focus, context.with_upgrade(2, 1));
+  }
+}
+
+impl CardBehavior for Amplify {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.action(RepeatNextCardsAction {
+      card_type: Power,
+      count: context.with_upgrade(2, 1),
+    });
+  }
+}
+
+impl CardBehavior for Eruption {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.attack_target(9);
+    context.action(ChangeStance(Stance::Wrath));
+  }
+}
+
+impl CardBehavior for Vigilance {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.block(context.with_upgrade(12, 8));
+    context.action(ChangeStance(Stance::Calm));
+  }
+}
+
+// returns to the hand from the discard pile whenever the stance changes; see `ChangeStance`
+impl CardBehavior for FlurryOfBlows {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.attack_target(context.with_upgrade(6, 4));
+  }
+  fn on_stance_change(self, runner: &mut Runner, card: &SingleCard) {
+    runner.action_bottom(DiscardToHand(card.clone()));
+  }
+}
+
+impl CardBehavior for Establishment {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.power_self(PowerId::Establishment, 1);
+  }
+}
+
+impl CardBehavior for FlyingSleeves {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    for _ in 0..2 {
+      context.attack_target(context.with_upgrade(6, 4));
+    }
+  }
+}
+
+impl CardBehavior for Protect {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.block(context.with_upgrade(16, 12));
+  }
+}
+
+impl CardBehavior for WreathOfFlame {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.power_self(PowerId::Vigor, context.with_upgrade(8, 5));
+  }
+}
+
+impl CardBehavior for Panache {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.power_self(PowerId::Panache, context.with_upgrade(14, 10));
+  }
+}
+
+impl CardBehavior for DeepBreath {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.action(ShuffleDiscardIntoDrawPile);
+    context.draw_cards(context.with_upgrade(2, 1));
+  }
+}
+
+impl CardBehavior for Finesse {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.block(context.with_upgrade(4, 2));
+    context.draw_cards(1);
+  }
+}
+
+impl CardBehavior for Apotheosis {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.action(ApotheosisAction);
+  }
+}
+
+impl CardBehavior for TheBomb {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.action(DelayAction {
+      turns: 3,
+      action: Box::new(
+        DamageAllEnemiesAction {
+          damage_type: DamageType::Thorns,
+          damage: context.with_upgrade(50, 40),
+        }
+        .into(),
+      ),
+    });
+  }
+}
+
+impl CardBehavior for HandOfGreed {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.action(HandOfGreedAction {
+      target: context.target(),
+      info: context.target_attack_info(context.with_upgrade(25, 20)),
+      gold: context.with_upgrade(25, 20),
+    });
+  }
+}
+
+impl CardBehavior for Shiv {
+  fn behavior(self, context: &mut impl CardBehaviorContext) {
+    context.attack_target(context.with_upgrade(6, 4));
+  }
+}
 
 impl CardBehavior for Injury {}
 impl CardBehavior for AscendersBane {}
+impl CardBehavior for Parasite {}
 impl CardBehavior for Dazed {}
 impl CardBehavior for Slimed {}
 impl CardBehavior for Burn {}
+impl CardBehavior for Wound {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::simulation_state::testing::*;
+
+  #[test]
+  fn combat_card_pool_only_has_cards_of_the_class() {
+    let red_attacks: Vec<CardId> = combat_card_pool(CardClass::Red, Attack).collect();
+    assert!(red_attacks.contains(&CardId::Anger));
+    assert!(!red_attacks.contains(&CardId::FlurryOfBlows));
+    assert!(!red_attacks.contains(&CardId::HandOfGreed));
+    assert!(!red_attacks.contains(&CardId::StrikeR));
+    assert!(combat_card_pool(CardClass::Purple, Attack).any(|id| id == CardId::FlurryOfBlows));
+  }
+
+  #[test]
+  fn infernal_blade_adds_a_free_attack_of_its_class() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![1])],
+      vec![card(CardId::InfernalBlade)],
+    );
+    play(&mut state, CardId::InfernalBlade, 0);
+    let generated = &state.hand[0];
+    assert_eq!(generated.card_info.class, CardClass::Red);
+    assert_eq!(generated.card_info.card_type, Attack);
+    assert_eq!(generated.cost_for_turn, Some(0));
+  }
+
+  #[test]
+  fn apotheosis_upgrades_every_card_once() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![1])],
+      vec![card(CardId::Apotheosis), card(CardId::BodySlam)],
+    );
+    state.draw_pile = vec![card(CardId::StrikeR), upgraded(CardId::Bash)];
+    state.discard_pile = vec![card(CardId::Wound), card(CardId::SearingBlow)];
+    play(&mut state, CardId::Apotheosis, 0);
+
+    assert_eq!(state.hand.as_slice(), &[upgraded(CardId::BodySlam)]);
+    assert_eq!(state.hand[0].cost, 0);
+    assert!(state.draw_pile.iter().all(|card| card.upgrades == 1));
+    assert_eq!(state.discard_pile, vec![card(CardId::Wound), upgraded(CardId::SearingBlow)]);
+    assert_eq!(state.exhaust_pile, vec![upgraded(CardId::Apotheosis)]);
+  }
+
+  #[test]
+  fn only_the_unupgraded_calculated_gamble_exhausts() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![1])],
+      vec![card(CardId::CalculatedGamble), card(CardId::DefendR)],
+    );
+    state.draw_pile = vec![card(CardId::StrikeR); 5];
+    play(&mut state, CardId::CalculatedGamble, 0);
+    assert_eq!(state.exhaust_pile, vec![card(CardId::CalculatedGamble)]);
+    assert_eq!(state.hand.len(), 1);
+
+    state.hand.push(upgraded(CardId::CalculatedGamble));
+    play(&mut state, CardId::CalculatedGamble, 0);
+    assert_eq!(state.exhaust_pile, vec![card(CardId::CalculatedGamble)]);
+    assert!(state.discard_pile.contains(&upgraded(CardId::CalculatedGamble)));
+    assert_eq!(state.hand.len(), 1);
+  }
+
+  #[test]
+  fn upgraded_darkness_charges_every_dark_orb() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![1])],
+      vec![card(CardId::Darkness), upgraded(CardId::Darkness)],
+    );
+    state.player.orb_slots = 3;
+    play(&mut state, CardId::Darkness, 0);
+    let charges = |state: &CombatState| state.player.orbs.iter().map(|orb| orb.misc).collect::<Vec<_>>();
+    assert_eq!(charges(&state), vec![6]);
+
+    play(&mut state, CardId::Darkness, 0);
+    assert_eq!(charges(&state), vec![12, 12]);
+  }
+
+  #[test]
+  fn each_hit_of_twin_strike_goes_through_the_block_left_by_the_last() {
+    let mut blocking = monster(MonsterId::Cultist, 50, vec![3]);
+    blocking.creature.block = 3;
+    let mut state = combat(vec![blocking], vec![card(CardId::TwinStrike)]);
+    play(&mut state, CardId::TwinStrike, 0);
+    // the first hit loses 3 to block, the second hits in full
+    assert_eq!(state.monsters[0].creature.block, 0);
+    assert_eq!(state.monsters[0].creature.hitpoints, 50 - (2 + 5));
+  }
+
+  #[test]
+  fn hand_of_greed_only_gives_gold_for_a_kill() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 20, vec![1]), monster(MonsterId::Cultist, 50, vec![1])],
+      vec![card(CardId::HandOfGreed), card(CardId::HandOfGreed)],
+    );
+    state.starting_gold = 99;
+    state.player.energy = 4;
+    play(&mut state, CardId::HandOfGreed, 1);
+    assert_eq!(state.gold(), 99);
+    play(&mut state, CardId::HandOfGreed, 0);
+    assert!(state.monsters[0].gone);
+    assert_eq!(state.gold_gained, 20);
+    assert_eq!(state.gold(), 119);
+  }
+
+  #[test]
+  fn flurry_of_blows_returns_to_hand_on_stance_change() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![1])],
+      vec![card(CardId::Eruption)],
+    );
+    state.discard_pile = vec![card(CardId::FlurryOfBlows), card(CardId::StrikeR), upgraded(CardId::FlurryOfBlows)];
+    play(&mut state, CardId::Eruption, 0);
+    assert_eq!(state.player.stance, Stance::Wrath);
+    assert_eq!(
+      state.hand.as_slice(),
+      &[card(CardId::FlurryOfBlows), upgraded(CardId::FlurryOfBlows)]
+    );
+    assert_eq!(state.discard_pile, vec![card(CardId::StrikeR), card(CardId::Eruption)]);
+  }
+
+  #[test]
+  fn retained_cards_stay_in_hand() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![1])],
+      vec![card(CardId::FlyingSleeves), card(CardId::StrikeR)],
+    );
+    end_turn(&mut state);
+    assert_eq!(state.hand.as_slice(), &[card(CardId::FlyingSleeves)]);
+    assert_eq!(state.discard_pile, vec![card(CardId::StrikeR)]);
+  }
+
+  #[test]
+  fn establishment_reduces_the_cost_of_retained_cards_each_turn() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![1])],
+      vec![card(CardId::Establishment), card(CardId::Protect)],
+    );
+    play(&mut state, CardId::Establishment, 0);
+    end_turn(&mut state);
+    assert_eq!(state.hand[0].cost, 1);
+    end_turn(&mut state);
+    assert_eq!(state.hand[0].card_info.id, CardId::Protect);
+    assert_eq!(state.hand[0].cost, 0);
+  }
+
+  #[test]
+  fn the_bomb_goes_off_at_the_end_of_the_third_turn() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 100, vec![3])],
+      vec![card(CardId::TheBomb)],
+    );
+    play(&mut state, CardId::TheBomb, 0);
+    for _ in 0..2 {
+      end_turn(&mut state);
+      assert_eq!(state.monsters[0].creature.hitpoints, 100);
+    }
+    end_turn(&mut state);
+    assert_eq!(state.monsters[0].creature.hitpoints, 100 - 40);
+    assert!(state.delayed_actions.is_empty());
+  }
+
+  #[test]
+  fn burst_plays_the_next_skill_twice_for_one_energy() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![3])],
+      vec![card(CardId::Burst), card(CardId::DefendR)],
+    );
+    play(&mut state, CardId::Burst, 0);
+    play(&mut state, CardId::DefendR, 0);
+    assert_eq!(state.player.creature.block, 10);
+    assert_eq!(state.player.energy, 1);
+    assert!(state.repeat_next.is_empty());
+    assert_eq!(state.discard_pile.len(), 2);
+  }
+
+  #[test]
+  fn double_tap_and_amplify_repeat_the_next_card_of_their_type() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![3])],
+      vec![
+        card(CardId::DoubleTap),
+        card(CardId::DefendR),
+        card(CardId::StrikeR),
+        card(CardId::Amplify),
+        card(CardId::Inflame),
+      ],
+    );
+    state.player.energy = 5;
+    play(&mut state, CardId::DoubleTap, 0);
+    // a skill doesn't use up Double Tap
+    play(&mut state, CardId::DefendR, 0);
+    assert_eq!(state.player.creature.block, 5);
+    play(&mut state, CardId::StrikeR, 0);
+    assert_eq!(state.monsters[0].creature.hitpoints, 50 - 12);
+
+    play(&mut state, CardId::Amplify, 0);
+    play(&mut state, CardId::Inflame, 0);
+    assert_eq!(state.player.creature.power_amount(PowerId::Strength), 4);
+    assert_eq!(state.player.energy, 0);
+    assert!(state.repeat_next.is_empty());
+  }
+
+  #[test]
+  fn cloak_and_dagger_makes_shivs_that_accuracy_boosts() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![3])],
+      vec![card(CardId::Accuracy), upgraded(CardId::CloakAndDagger)],
+    );
+    play(&mut state, CardId::Accuracy, 0);
+    play(&mut state, CardId::CloakAndDagger, 0);
+    assert_eq!(state.player.creature.block, 6);
+    assert_eq!(state.hand.to_vec(), vec![card(CardId::Shiv), card(CardId::Shiv)]);
+    play(&mut state, CardId::Shiv, 0);
+    play(&mut state, CardId::Shiv, 0);
+    assert_eq!(state.monsters[0].creature.hitpoints, 50 - 2 * (4 + 4));
+    assert_eq!(state.exhaust_pile.len(), 2);
+    assert_eq!(state.player.energy, 1);
+  }
+
+  #[test]
+  fn bloodletting_trades_hitpoints_for_energy() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![3])],
+      vec![card(CardId::Bloodletting)],
+    );
+    play(&mut state, CardId::Bloodletting, 0);
+    assert_eq!(state.player.creature.hitpoints, 77);
+    assert_eq!(state.player.energy, 5);
+    assert_eq!(state.discard_pile, vec![card(CardId::Bloodletting)]);
+  }
+
+  #[test]
+  fn hemokinesis_loses_hitpoints_and_attacks() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![3])],
+      vec![card(CardId::Hemokinesis)],
+    );
+    play(&mut state, CardId::Hemokinesis, 0);
+    assert_eq!(state.player.creature.hitpoints, 78);
+    assert_eq!(state.monsters[0].creature.hitpoints, 50 - 14);
+  }
+
+  #[test]
+  fn true_grit_exhausts_a_random_card_from_the_rest_of_the_hand() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![3])],
+      vec![card(CardId::TrueGrit), card(CardId::StrikeR), card(CardId::DefendR)],
+    );
+    play(&mut state, CardId::TrueGrit, 0);
+    assert_eq!(state.player.creature.block, 7);
+    assert_eq!(state.hand.len(), 1);
+    assert_eq!(state.exhaust_pile.len(), 1);
+    assert_ne!(state.hand[0], state.exhaust_pile[0]);
+
+    let hand = vec![card(CardId::StrikeR), card(CardId::DefendR)];
+    let before = combat(vec![monster(MonsterId::Cultist, 50, vec![3])], hand.clone());
+    match ExhaustRandomCardAction.determinism(&before) {
+      Determinism::Random(distribution) => assert_eq!(distribution.0.len(), 2),
+      _ => panic!("exhausting from a hand of 2 should be random"),
+    }
+    for (index, exhausted) in hand.iter().enumerate() {
+      let mut outcome = before.clone();
+      Runner::new(&mut outcome, false, false).apply_outcome(&ExhaustRandomCardAction, index as i32);
+      assert_eq!(&outcome.exhaust_pile, &vec![exhausted.clone()]);
+    }
+  }
+
+  #[test]
+  fn whirlwind_hits_once_per_energy_spent() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![3])],
+      vec![card(CardId::Whirlwind), card(CardId::Whirlwind)],
+    );
+    play(&mut state, CardId::Whirlwind, 0);
+    assert_eq!(state.monsters[0].creature.hitpoints, 50 - 3 * 5);
+    assert_eq!(state.player.energy, 0);
+
+    // still playable at 0 energy, for no hits
+    let whirlwind = Choice::from(PlayCard {
+      card: card(CardId::Whirlwind),
+      target: 0,
+    });
+    assert!(state.legal_choices().contains(&whirlwind));
+    play(&mut state, CardId::Whirlwind, 0);
+    assert_eq!(state.monsters[0].creature.hitpoints, 50 - 3 * 5);
+    assert_eq!(state.player.energy, 0);
+    assert_eq!(state.discard_pile.len(), 2);
+  }
+
+  #[test]
+  fn rebound_puts_the_next_card_on_top_of_the_draw_pile() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![3])],
+      vec![card(CardId::Rebound), card(CardId::StrikeR), card(CardId::DefendR)],
+    );
+    state.draw_pile = vec![card(CardId::Bash)];
+    play(&mut state, CardId::Rebound, 0);
+    assert_eq!(state.discard_pile, vec![card(CardId::Rebound)]);
+    play(&mut state, CardId::StrikeR, 0);
+    assert_eq!(state.draw_pile.last(), Some(&card(CardId::StrikeR)));
+    assert_eq!(state.draw_pile_known_top, 1);
+    assert!(!state.player.creature.has_power(PowerId::Rebound));
+    play(&mut state, CardId::DefendR, 0);
+    assert_eq!(state.discard_pile, vec![card(CardId::Rebound), card(CardId::DefendR)]);
+  }
+
+  #[test]
+  fn setup_puts_the_chosen_card_on_top_of_the_draw_pile_for_free() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![3])],
+      vec![card(CardId::Setup), card(CardId::Bash), card(CardId::StrikeR)],
+    );
+    state.draw_pile = (0..5).map(|_| card(CardId::DefendR)).collect();
+    play(&mut state, CardId::Setup, 0);
+    assert!(state.pending_choice().is_some());
+    run(
+      &mut state,
+      &ChooseCard {
+        choice_type: CardChoiceType::SetupTopdeck,
+        card_index: 0,
+      },
+    );
+    let bash = state.draw_pile.last().unwrap().clone();
+    assert_eq!(bash.card_info.id, CardId::Bash);
+    assert_eq!(state.draw_pile_known_top, 1);
+    assert_eq!(state.effective_cost(&bash), 0);
+
+    end_turn(&mut state);
+    let bash = state.hand.iter().find(|card| card.card_info.id == CardId::Bash).unwrap();
+    assert_eq!(state.effective_cost(bash), 0);
+  }
+
+  #[test]
+  fn deep_breath_shuffles_the_discard_pile_back_and_draws() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![3])],
+      vec![card(CardId::DeepBreath)],
+    );
+    state.draw_pile = vec![card(CardId::Bash)];
+    state.discard_pile = (0..4).map(|_| card(CardId::StrikeR)).collect();
+    play(&mut state, CardId::DeepBreath, 0);
+    assert_eq!(state.hand.len(), 1);
+    assert!(state.discard_pile.iter().all(|card| card.card_info.id == CardId::DeepBreath));
+    let strikes = state
+      .hand
+      .iter()
+      .chain(&state.draw_pile)
+      .filter(|card| card.card_info.id == CardId::StrikeR)
+      .count();
+    assert_eq!(strikes, 4);
+    assert!(state.draw_pile.len() >= 4);
+  }
+
+  #[test]
+  fn acrobatics_draws_and_then_requires_a_discard() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![3])],
+      vec![card(CardId::Acrobatics), card(CardId::StrikeR)],
+    );
+    state.draw_pile = (0..5).map(|_| card(CardId::DefendR)).collect();
+    play(&mut state, CardId::Acrobatics, 0);
+    assert_eq!(state.hand.len(), 4);
+    let choices = state.legal_choices();
+    assert!(!choices.contains(&Choice::from(EndTurn)));
+    assert!(!choices.contains(&Choice::from(StopChoosingCards)));
+    run(
+      &mut state,
+      &ChooseCard {
+        choice_type: CardChoiceType::DiscardCard,
+        card_index: 0,
+      },
+    );
+    assert_eq!(state.hand.len(), 3);
+    assert!(state.discard_pile.contains(&card(CardId::StrikeR)));
+    assert!(state.pending_choice().is_none());
+  }
+
+  #[test]
+  fn finesse_blocks_and_draws() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![3])],
+      vec![card(CardId::Finesse)],
+    );
+    state.draw_pile = vec![card(CardId::StrikeR)];
+    play(&mut state, CardId::Finesse, 0);
+    assert_eq!(state.player.creature.block, 2);
+    assert_eq!(state.hand.as_slice(), &[card(CardId::StrikeR)]);
+    assert_eq!(state.player.energy, 3);
+  }
+}
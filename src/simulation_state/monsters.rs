@@ -2,7 +2,7 @@
 
 use serde::{Deserialize, Serialize};
 use std::convert::From;
-use smallvec::SmallVec;
+use smallvec::{smallvec, SmallVec};
 
 use crate::simulation::*;
 use crate::simulation_state::*;
@@ -39,9 +39,6 @@ impl<'a> IntentChoiceContext<'a> {
   pub fn always(&mut self, value: impl Into<Distribution>) {
     self.if_num_lt(100, value);
   }
-  pub fn first_move(&self) -> bool {
-    self.monster.move_history.is_empty()
-  }
   pub fn last_intent(&self) -> Option<i32> {
     self.monster.move_history.last().cloned()
   }
@@ -78,6 +75,29 @@ impl<'a> IntentChoiceContext<'a> {
     }
   }
 
+  /// Picks from weighted `(intent, weight, max_repeats)` moves, leaving out any move that was already
+  /// used `max_repeats` times in a row; the remaining weights are scaled back up to add up to 1.
+  /// If every move is ruled out, falls back to all of them.
+  pub fn weighted_with_max_repeats(&self, moves: &[(i32, f64, Repeats)]) -> Distribution {
+    let allowed: SmallVec<[(f64, i32); 4]> = moves
+      .iter()
+      .filter(|&&(intent, _, max_repeats)| !self.did_repeats(max_repeats, intent))
+      .map(|&(intent, weight, _)| (weight, intent))
+      .collect();
+    let allowed = if allowed.is_empty() {
+      moves.iter().map(|&(intent, weight, _)| (weight, intent)).collect()
+    } else {
+      allowed
+    };
+    let total: f64 = allowed.iter().map(|&(weight, _)| weight).sum();
+    Distribution(
+      allowed
+        .into_iter()
+        .map(|(weight, intent)| (weight / total, intent))
+        .collect(),
+    )
+  }
+
   fn ascension(&self) -> i32 {
     self.ascension
   }
@@ -112,6 +132,11 @@ pub fn intent_choice_distribution(state: &CombatState, monster_index: usize) ->
     ascension: monster.ascension,
     num_distribution: Vec::new(),
   };
+  if monster.move_history.is_empty() {
+    if let Some(distribution) = monster_id.first_move(&context) {
+      return distribution;
+    }
+  }
   monster_id.make_intent_distribution(&mut context);
   context.final_distribution()
 }
@@ -173,8 +198,9 @@ pub trait IntentEffectsContext {
 
   fn attack(&mut self, base_damage: i32) {
     // hack: this is actually NOT where powers are applied to card/monster damage in the actual code
-    let mut info = DamageInfo::new(self.creature_index(), base_damage, DamageType::Normal);
-    info.apply_powers(self.state(), self.creature_index(), CreatureIndex::Player);
+    let mut info = DamageInfo::new(self.creature_index(), base_damage, DamageType::Normal)
+      .with_source(DamageSource::MonsterMove(self.intent()));
+    info.apply_powers(self.state(), self.creature_index(), CreatureIndex::Player, None);
     self.action(DamageAction {
       info,
       target: CreatureIndex::Player,
@@ -207,6 +233,12 @@ pub trait IntentEffectsContext {
       self.action(DiscardNewCard(SingleCard::create(card_id)));
     }
   }
+  /// Shuffles `amount` copies of a status card into the draw pile at random positions.
+  fn shuffle_status(&mut self, card_id: CardId, amount: i32) {
+    for _ in 0..amount {
+      self.action(DrawPileNewCard(SingleCard::create(card_id)));
+    }
+  }
 
   fn undefined_intent(&mut self) {}
 }
@@ -251,7 +283,84 @@ pub fn intent_actions (state: &CombatState, monster_index: usize) -> SmallVec<[D
   context.actions
 }
 
+/// A rough classification of what a monster's current intent will do, like the intent icon in the game.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub enum IntentKind {
+  /// Like the game's intent display: `damage` is per hit, after both sides' powers
+  /// (so it includes e.g. the player's Vulnerable), and the attack hits `hits` times.
+  Attack { damage: i32, hits: i32 },
+  Debuff,
+  /// Gains block (for itself or an ally) without attacking, so it contributes no incoming damage.
+  /// The block is gained during the monster's turn and lasts through the player's next turn.
+  Defend,
+  Buff,
+  Nothing,
+}
+
+/// A classified intent, along with a hash of everything the classification read (see
+/// `CombatState::intent_inputs`), so it only has to be redone once one of those changes.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct IntentCache {
+  pub inputs: u64,
+  pub kind: IntentKind,
+}
+
+impl IntentKind {
+  pub fn incoming_damage(&self) -> i32 {
+    match *self {
+      IntentKind::Attack { damage, hits } => damage * hits,
+      _ => 0,
+    }
+  }
+}
+
+pub fn classify_intent(state: &CombatState, monster_index: usize) -> IntentKind {
+  let mut damage = 0;
+  let mut hits = 0;
+  let mut debuff = false;
+  let mut defend = false;
+  let mut buff = false;
+  for action in intent_actions(state, monster_index) {
+    match action {
+      DynAction::DamageAction(action) => {
+        if action.target == CreatureIndex::Player && action.info.damage_type == DamageType::Normal {
+          if hits == 0 {
+            damage = action.info.output;
+          }
+          hits += 1;
+        }
+      }
+      DynAction::ApplyPowerAction(action) => {
+        if action.target == CreatureIndex::Player && action.is_debuff() {
+          debuff = true;
+        } else {
+          buff = true;
+        }
+      }
+      DynAction::DiscardNewCard(_) | DynAction::DrawPileNewCard(_) => debuff = true,
+      DynAction::GainBlockAction(_) | DynAction::GainBlockRandomMonsterAction(_) => defend = true,
+      _ => {}
+    }
+  }
+  if hits > 0 {
+    IntentKind::Attack { damage, hits }
+  } else if debuff {
+    IntentKind::Debuff
+  } else if defend {
+    IntentKind::Defend
+  } else if buff {
+    IntentKind::Buff
+  } else {
+    IntentKind::Nothing
+  }
+}
+
 pub trait MonsterBehavior: Sized + Copy + Into<MonsterId> {
+  /// The monster's fixed opening move, if it has one. Only used while `move_history` is empty;
+  /// a monster read from the game already has its observed move in its history.
+  fn first_move(self, context: &IntentChoiceContext) -> Option<Distribution> {
+    None
+  }
   fn make_intent_distribution(self, context: &mut IntentChoiceContext);
 
   fn after_choosing_intent(self, runner: &mut Runner, monster_index: usize) {}
@@ -284,6 +393,11 @@ macro_rules! monsters {
     }
 
     impl MonsterBehavior for MonsterId {
+      fn first_move (self, context: &IntentChoiceContext)->Option<Distribution> {
+        match self {
+        $(MonsterId::$Variant => $Variant.first_move (context),)*
+        }
+      }
       fn make_intent_distribution (self, context: &mut IntentChoiceContext) {
         match self {
         $(MonsterId::$Variant => $Variant.make_intent_distribution (context),)*
@@ -333,11 +447,18 @@ monsters! {
   ["SlimeBoss", SlimeBoss],
   
   ["Byrd", Byrd],
+
+  ["WrithingMass", WrithingMass],
+  ["Nemesis", Nemesis],
+  ["Repulsor", Repulsor],
 }
 
 impl MonsterBehavior for Cultist {
+  fn first_move(self, context: &IntentChoiceContext) -> Option<Distribution> {
+    Some(3.into())
+  }
   fn make_intent_distribution(self, context: &mut IntentChoiceContext) {
-    context.always(if context.first_move() { 3 } else { 1 });
+    context.always(1);
   }
   fn intent_effects(self, context: &mut impl IntentEffectsContext) {
     match context.intent() {
@@ -397,10 +518,10 @@ impl MonsterBehavior for GreenLouse {
 }
 
 impl MonsterBehavior for JawWorm {
+  fn first_move(self, context: &IntentChoiceContext) -> Option<Distribution> {
+    Some(1.into())
+  }
   fn make_intent_distribution(self, context: &mut IntentChoiceContext) {
-    if context.first_move() {
-      context.always(1);
-    }
     context.if_num_lt(
       25,
       context.with_max_repeats(Repeats(1), 1, Distribution::split(0.5625, 2, 3)),
@@ -642,12 +763,10 @@ impl MonsterBehavior for SlaverBlue {
   }
 }
 impl MonsterBehavior for SlaverRed {
+  fn first_move(self, context: &IntentChoiceContext) -> Option<Distribution> {
+    Some(1.into())
+  }
   fn make_intent_distribution(self, context: &mut IntentChoiceContext) {
-    if context.first_move() {
-      context.always(1);
-      return;
-    }
-
     if context
       .monster()
       .move_history
@@ -784,12 +903,10 @@ impl MonsterBehavior for Sentry {
   }
 }
 impl MonsterBehavior for GremlinNob {
+  fn first_move(self, context: &IntentChoiceContext) -> Option<Distribution> {
+    Some(3.into())
+  }
   fn make_intent_distribution(self, context: &mut IntentChoiceContext) {
-    if context.first_move() {
-      context.always(3);
-      return;
-    }
-
     if context.ascension() >= 18 {
       if (context.state().turn_number % 3) == 2 {
         context.always(2);
@@ -971,11 +1088,11 @@ impl MonsterBehavior for SlimeBoss {
 
 
 impl MonsterBehavior for Byrd {
+  fn first_move(self, context: &IntentChoiceContext) -> Option<Distribution> {
+    Some(Distribution::split(0.375, 6, 1))
+  }
   fn make_intent_distribution(self, context: &mut IntentChoiceContext) {
-    if context.first_move() {
-      context.always (Distribution::split (0.375, 6, 1));
-    }
-    else if context.monster().creature.has_power (PowerId::Flight) {
+    if context.monster().creature.has_power (PowerId::Flight) {
       context.if_num_lt (50, context.with_max_repeats (Repeats (2), 1, Distribution::split (0.4, 3, 6)));
       context.if_num_lt (70, context.with_max_repeats (Repeats (1), 3, Distribution::split (0.375, 6, 1)));
       context.else_num (context.with_max_repeats (Repeats (1), 63, Distribution::split (0.2857, 3, 1)));
@@ -998,3 +1115,236 @@ impl MonsterBehavior for Byrd {
     }
   }
 }
+
+// The game re-rolls Writhing Mass's intent the moment it's hit. Here, `Reactive` only records the hit in its
+// `misc`, and the next move is picked from the attacks alone if it was hit since its last move.
+impl MonsterBehavior for WrithingMass {
+  fn first_move(self, context: &IntentChoiceContext) -> Option<Distribution> {
+    Some(Distribution(smallvec![(0.33, 1), (0.33, 2), (0.34, 3)]))
+  }
+  fn make_intent_distribution(self, context: &mut IntentChoiceContext) {
+    let attacked = context
+      .monster
+      .creature
+      .powers
+      .iter()
+      .any(|power| power.power_id == PowerId::Reactive && power.misc > 0);
+    // approximates the game's re-rolls when a move can't repeat as spreading its weight over the others
+    let mut moves = vec![(0, 0.1, Repeats(1)), (1, 0.3, Repeats(1))];
+    if !attacked {
+      moves.extend_from_slice(&[(3, 0.2, Repeats(1)), (2, 0.3, Repeats(1))]);
+      if !context.monster.move_history.contains(&4) {
+        moves.push((4, 0.1, Repeats(1)));
+      }
+    }
+    context.always(context.weighted_with_max_repeats(&moves));
+  }
+  fn after_choosing_intent(self, runner: &mut Runner, monster_index: usize) {
+    for power in &mut runner.state_mut().monsters[monster_index].creature.powers {
+      if power.power_id == PowerId::Reactive {
+        power.misc = 0;
+      }
+    }
+  }
+  fn intent_effects(self, context: &mut impl IntentEffectsContext) {
+    match context.intent() {
+      0 => context.attack(context.with_ascension(Ascension(2), 35, 32)),
+      1 => for _ in 0..3 {
+        context.attack(context.with_ascension(Ascension(2), 9, 7));
+      }
+      2 => {
+        context.attack(context.with_ascension(Ascension(2), 16, 15));
+        context.block(context.with_ascension(Ascension(2), 16, 15));
+      }
+      3 => {
+        context.attack(context.with_ascension(Ascension(2), 12, 10));
+        context.power_player(PowerId::Weak, 2);
+        context.power_player(PowerId::Vulnerable, 2);
+      }
+      // the game puts the Parasite in the master deck; for this combat, it goes in the discard pile
+      4 => context.discard_status(CardId::Parasite, 1),
+      _ => context.undefined_intent(),
+    }
+  }
+}
+
+impl MonsterBehavior for Nemesis {
+  fn first_move(self, context: &IntentChoiceContext) -> Option<Distribution> {
+    Some(Distribution::split(0.5, 2, 4))
+  }
+  fn make_intent_distribution(self, context: &mut IntentChoiceContext) {
+    // Scythe has a cooldown that, in practice, only forbids using it twice in a row
+    let last_scythe = context.last_intent() == Some(3);
+    if last_scythe {
+      context.if_num_lt(30, Distribution::split(0.5, 2, 4));
+    } else {
+      context.if_num_lt(30, 3);
+    }
+    context.if_num_lt(65, context.with_max_repeats(Repeats(2), 2, Distribution::split(0.5, 3, 4)));
+    context.else_num(context.with_max_repeats(Repeats(1), 4, Distribution::split(0.5, 3, 2)));
+  }
+  fn intent_effects(self, context: &mut impl IntentEffectsContext) {
+    match context.intent() {
+      2 => for _ in 0..3 {
+        context.attack(context.with_ascension(Ascension(3), 7, 6));
+      }
+      3 => context.attack(45),
+      4 => context.discard_status(CardId::Burn, context.with_ascension(Ascension(18), 5, 3)),
+      _ => context.undefined_intent(),
+    }
+    // Intangible lasts through the player's next turn, so this alternates every other turn
+    if !context.monster().creature.has_power(PowerId::Intangible) {
+      context.power_self(PowerId::Intangible, 1);
+    }
+  }
+}
+
+impl MonsterBehavior for Repulsor {
+  fn make_intent_distribution(self, context: &mut IntentChoiceContext) {
+    context.if_num_lt(20, context.with_max_repeats(Repeats(1), 2, 1));
+    context.else_num(1);
+  }
+  fn intent_effects(self, context: &mut impl IntentEffectsContext) {
+    match context.intent() {
+      1 => context.shuffle_status(CardId::Dazed, 2),
+      2 => context.attack(context.with_ascension(Ascension(2), 13, 11)),
+      _ => context.undefined_intent(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::simulation_state::testing::*;
+
+  #[test]
+  fn a_fresh_cultist_opens_with_incantation() {
+    let mut state = combat(vec![monster(MonsterId::Cultist, 50, Vec::new())], Vec::new());
+    assert_eq!(state.predicted_next_intent(0), Distribution::from(3));
+    run(&mut state, &ChooseMonsterIntent(0));
+    assert_eq!(state.monsters[0].move_history, vec![3]);
+  }
+
+  #[test]
+  fn attacking_writhing_mass_limits_its_next_move_to_attacks() {
+    let mut mass = monster(MonsterId::WrithingMass, 160, vec![2]);
+    mass.creature.powers.push(power(PowerId::Reactive, 0));
+    let mut state = combat(vec![mass], vec![card(CardId::StrikeR)]);
+    let unprovoked = state.predicted_next_intent(0);
+    assert!(unprovoked.0.iter().any(|&(_, intent)| intent == 3));
+
+    play(&mut state, CardId::StrikeR, 0);
+    let provoked = state.predicted_next_intent(0);
+    assert_ne!(provoked, unprovoked);
+    assert!(provoked.0.iter().all(|&(_, intent)| intent == 0 || intent == 1));
+
+    end_turn(&mut state);
+    let mass = &state.monsters[0];
+    assert!(mass.intent() == 0 || mass.intent() == 1);
+    assert_eq!(mass.creature.powers[0].misc, 0);
+  }
+
+  #[test]
+  fn writhing_mass_implants_a_parasite() {
+    let mut state = combat(vec![monster(MonsterId::WrithingMass, 160, vec![4])], Vec::new());
+    end_turn(&mut state);
+    // it was shuffled back into the draw pile and drawn at the start of the turn
+    assert_eq!(state.hand.as_slice(), &[card(CardId::Parasite)]);
+  }
+
+  #[test]
+  fn monster_debuffs_apply_to_the_player() {
+    let mut state = combat(
+      vec![
+        monster(MonsterId::SpikeSlimeL, 64, vec![4]),
+        monster(MonsterId::FatGremlin, 14, vec![2]),
+      ],
+      Vec::new(),
+    );
+    assert_eq!(state.intent_kind(0), IntentKind::Debuff);
+    end_turn(&mut state);
+    assert_eq!(state.player.creature.power_amount(PowerId::Frail), 2);
+    assert_eq!(state.player.creature.power_amount(PowerId::Weak), 1);
+    assert_eq!(state.player.creature.hitpoints, 80 - 4);
+  }
+
+  #[test]
+  fn a_multi_hit_intent_reports_each_hit_and_the_total() {
+    let mut state = combat(vec![monster(MonsterId::Nemesis, 185, vec![2])], Vec::new());
+    assert_eq!(state.intent_kind(0), IntentKind::Attack { damage: 6, hits: 3 });
+    assert_eq!(state.incoming_damage(), 18);
+    end_turn(&mut state);
+    assert_eq!(state.player.creature.hitpoints, 80 - 18);
+  }
+
+  #[test]
+  fn a_move_used_up_to_its_cap_is_not_picked_again() {
+    let once = combat(vec![monster(MonsterId::Nemesis, 185, vec![4, 2])], Vec::new());
+    assert!(once.predicted_next_intent(0).0.iter().any(|&(_, intent)| intent == 2));
+    let twice = combat(vec![monster(MonsterId::Nemesis, 185, vec![2, 2])], Vec::new());
+    assert!(twice.predicted_next_intent(0).0.iter().all(|&(_, intent)| intent != 2));
+  }
+
+  #[test]
+  fn a_50_50_first_move_is_predicted_without_choosing_it() {
+    let state = combat(vec![monster(MonsterId::Nemesis, 185, Vec::new())], Vec::new());
+    let predicted = state.predicted_next_intent(0);
+    let probability = |move_id| -> f64 {
+      predicted
+        .0
+        .iter()
+        .filter(|&&(_, intent)| intent == move_id)
+        .map(|&(weight, _)| weight)
+        .sum()
+    };
+    assert!((probability(2) - 0.5).abs() < 1e-9);
+    assert!((probability(4) - 0.5).abs() < 1e-9);
+    assert!(state.monsters[0].move_history.is_empty());
+  }
+
+  #[test]
+  fn nemesis_is_intangible_every_other_turn() {
+    let mut state = combat(
+      vec![monster(MonsterId::Nemesis, 185, vec![4])],
+      vec![card(CardId::StrikeR)],
+    );
+    state.player.creature.hitpoints = 500;
+    state.player.creature.max_hitpoints = 500;
+    state.draw_pile = (0..30).map(|_| card(CardId::StrikeR)).collect();
+    let mut damage_dealt = Vec::new();
+    for _ in 0..4 {
+      let intangible = state.monsters[0].creature.has_power(PowerId::Intangible);
+      let hitpoints = state.monsters[0].creature.hitpoints;
+      play(&mut state, CardId::StrikeR, 0);
+      damage_dealt.push((intangible, hitpoints - state.monsters[0].creature.hitpoints));
+      end_turn(&mut state);
+    }
+    assert_eq!(damage_dealt, vec![(false, 6), (true, 1), (false, 6), (true, 1)]);
+  }
+
+  #[test]
+  fn entangled_allows_everything_but_attacks_until_the_turn_ends() {
+    let mut state = combat(vec![monster(MonsterId::SlaverRed, 46, vec![2])], Vec::new());
+    state.draw_pile = (0..3)
+      .map(|_| card(CardId::StrikeR))
+      .chain((0..2).map(|_| card(CardId::DefendR)))
+      .collect();
+    end_turn(&mut state);
+    assert!(state.player.creature.has_power(PowerId::Entangled));
+    assert_eq!(state.hand.len(), 5);
+    let choices = state.legal_choices();
+    assert!(choices.contains(&Choice::from(EndTurn)));
+    assert!(choices.contains(&Choice::from(PlayCard {
+      card: card(CardId::DefendR),
+      target: 0,
+    })));
+    assert!(choices.iter().all(|choice| match choice {
+      DynAction::PlayCard(PlayCard { card, .. }) => card.card_info.card_type != CardType::Attack,
+      _ => true,
+    }));
+
+    end_turn(&mut state);
+    assert!(!state.player.creature.has_power(PowerId::Entangled));
+  }
+}
@@ -91,6 +91,9 @@ impl<'a, 'b> PowerHookContext<'a, 'b> {
     }
   }
 
+  pub fn action_now(&mut self, action: &impl Action) {
+    self.runner.action_now(action);
+  }
   pub fn action_top(&mut self, action: impl Action) {
     self.runner.action_top(action);
   }
@@ -141,6 +144,11 @@ pub trait PowerBehavior {
   fn priority(&self) -> i32 {
     5
   }
+  /// Whether the power loses 1 from its amount at the end of each round, like Vulnerable
+  /// (unlike Strength, or Poison, which goes down at the start of its owner's turn).
+  fn ticks_down(&self) -> bool {
+    false
+  }
   fn stack_power(&self, power: &mut Power, stack_amount: i32) {
     if (power.amount == -1) {
       return;
@@ -151,11 +159,24 @@ pub trait PowerBehavior {
     power.amount = std::cmp::max(0, power.amount - reduce_amount);
   }
 
+  /// Flat bonuses like Strength; they all apply before any `at_damage_give` multiplier.
+  /// `card` is the card dealing the damage, if it comes from one.
+  fn at_damage_give_flat(
+    &self,
+    context: &PowerNumericHookContext,
+    damage: f64,
+    damage_type: DamageType,
+    card: Option<&SingleCard>,
+  ) -> f64 {
+    damage
+  }
+  /// Multipliers like Weak, applied after every flat bonus.
   fn at_damage_give(
     &self,
     context: &PowerNumericHookContext,
     damage: f64,
     damage_type: DamageType,
+    card: Option<&SingleCard>,
   ) -> f64 {
     damage
   }
@@ -181,7 +202,12 @@ pub trait PowerBehavior {
   fn at_end_of_turn(&self, context: &mut PowerHookContext) {}
   fn at_end_of_round(&self, context: &mut PowerHookContext) {}
   fn on_attacked(&self, context: &mut PowerHookContext, info: DamageInfo, damage: i32) {}
-  fn on_attack(&self, context: &mut PowerHookContext, damage: i32, target: CreatureIndex) {}
+  /// Called on the powers of the damage source, with the damage left over after block.
+  fn on_attack(&self, context: &mut PowerHookContext, info: DamageInfo, damage: i32, target: CreatureIndex) {}
+  /// Called on the attacker's powers with the damage left over after block.
+  fn on_attack_to_change_damage(&self, context: &PowerNumericHookContext, info: &DamageInfo, damage: i32) -> i32 {
+    damage
+  }
   fn on_attacked_to_change_damage(&self, context: &PowerNumericHookContext, damage: i32) -> i32 {
     damage
   }
@@ -193,9 +219,18 @@ pub trait PowerBehavior {
   fn on_death(&self, context: &mut PowerHookContext) {}
   fn at_energy_gain(&self, context: &mut PowerHookContext) {}
   fn on_exhaust(&self, context: &mut PowerHookContext, card: &SingleCard) {}
+  /// Called on the powers of a creature that just applied a debuff to a different creature.
+  fn on_debuff_applied(&self, context: &mut PowerHookContext, target: CreatureIndex, power_id: PowerId) {}
   fn modify_block(&self, context: &PowerNumericHookContext, block: f64) -> f64 {
     block
   }
+  /// How much of `block` the owner keeps at the start of its turn, given that the powers before this one
+  /// let it keep `retained`. Without any powers changing it, block resets to 0.
+  fn modify_block_retention(&self, context: &PowerNumericHookContext, block: i32, retained: i32) -> i32 {
+    retained
+  }
+  /// Fired by `GainBlockAction` once per gain of a positive amount of block. A reaction that gained
+  /// block itself would trigger this again, so none of them do (Juggernaut deals damage instead).
   fn on_gained_block(&self, context: &mut PowerHookContext, block: f64) {}
   fn on_remove(&self, context: &mut PowerHookContext) {}
   fn on_energy_recharge(&self, context: &mut PowerHookContext) {}
@@ -233,6 +268,8 @@ macro_rules! powers {
       }
     }
 
+    pub const ALL_POWER_IDS: &[PowerId] = &[$(PowerId::$Variant,)*];
+
     impl PowerId {
       pub fn power_type(&self)->PowerType {
         match self {
@@ -250,22 +287,33 @@ powers! {
   ["Strength", Strength, Buff],
   ["Vulnerable", Vulnerable, Debuff],
   ["Weakened", Weak, Debuff],
+  ["Poison", Poison, Debuff],
+  ["Focus", Focus, Buff],
   
   // Less common powers that are still shared with more than one card/relic/monster
   ["Thorns", Thorns, Buff],
   ["Metallicize", Metallicize, Buff],
   ["No Draw", NoDraw, Debuff],
+  ["Draw Reduction", DrawReduction, Debuff],
   ["Plated Armor", PlatedArmor, Buff],
+  ["Energized", Energized, Buff],
+  ["Vigor", Vigor, Buff],
   
   // Relics
+  ["Boot", Boot, Relic],
   ["Busted Crown", BustedCrown, Relic],
+  ["Calipers", Calipers, Relic],
   ["Coffee Dripper", CoffeeDripper, Relic],
   ["Cursed Key", CursedKey, Relic],
   ["Ectoplasm", Ectoplasm, Relic],
   ["Fusion Hammer", FusionHammer, Relic],
   ["Mark of Pain", MarkOfPain, Relic],
+  // the game uses "Pen Nib" for both the relic and its power; see `From<&communication::Relic> for Power`
+  ["Pen Nib (relic)", PenNibRelic, Relic],
   ["Philosopher's Stone", PhilosophersStone, Relic],
   ["Sozu", Sozu, Relic],
+  ["StrikeDummy", StrikeDummy, Relic],
+  ["WristBlade", WristBlade, Relic],
   
   // Relic powers
   ["Pen Nib", PenNib, Buff],
@@ -289,6 +337,26 @@ powers! {
   ["Double Tap", DoubleTap, Buff],
   ["Juggernaut", Juggernaut, Buff],
 
+  // Silent card powers
+  ["Tools Of The Trade", ToolsOfTheTrade, Buff],
+  ["Sadistic", SadisticNature, Buff],
+  ["Envenom", Envenom, Buff],
+  ["Noxious Fumes", NoxiousFumes, Buff],
+  ["Burst", Burst, Buff],
+  ["Accuracy", Accuracy, Buff],
+
+  // Defect card powers
+  ["Amplify", Amplify, Buff],
+  ["Buffer", Buffer, Buff],
+  ["Rebound", Rebound, Buff],
+
+  // Watcher card powers
+  ["EstablishmentPower", Establishment, Buff],
+
+  // Colorless card powers
+  ["Panache", Panache, Buff],
+  ["IntangiblePlayer", IntangiblePlayer, Buff],
+
   // Exordium monster powers
   ["Ritual", Ritual, Buff],
   ["Curl Up", CurlUp, Buff],
@@ -301,17 +369,33 @@ powers! {
   // Exordium elite powers
   ["Anger", Enrage, Buff],
   ["Artifact", Artifact, Buff],
+
+  // Exordium boss powers
+  ["Sharp Hide", SharpHide, Buff],
   
   // City monster powers
   ["Flight", Flight, Buff],
+
+  // City elite powers
+  ["Painful Stabs", PainfulStabs, Buff],
+
+  // Beyond monster powers
+  ["Reactive", Reactive, Buff],
+  ["Malleable", Malleable, Buff],
+
+  // Beyond elite powers
+  ["Intangible", Intangible, Buff],
+
+  // Beyond boss powers
+  ["Time Warp", TimeWarp, Buff],
   
 
   ["Unknown", Unknown, Buff],
 }
 
 impl PowerBehavior for Vulnerable {
-  fn at_end_of_round(&self, context: &mut PowerHookContext) {
-    context.reduce_this_power();
+  fn ticks_down(&self) -> bool {
+    true
   }
   fn at_damage_receive(
     &self,
@@ -326,30 +410,34 @@ impl PowerBehavior for Vulnerable {
   }
 }
 
+// `modify_block` is only applied to block from cards (see `CardBehaviorContext::block`), so like in the game,
+// block from powers, relics and orbs isn't reduced by Frail.
 impl PowerBehavior for Frail {
+  fn ticks_down(&self) -> bool {
+    true
+  }
   fn priority(&self) -> i32 {
     10
   }
-  fn at_end_of_round(&self, context: &mut PowerHookContext) {
-    context.reduce_this_power();
-  }
   fn modify_block(&self, _context: &PowerNumericHookContext, block: f64) -> f64 {
     block * 0.75
   }
 }
 
 impl PowerBehavior for Weak {
+  fn ticks_down(&self) -> bool {
+    true
+  }
+  // a Strike with 2 Strength deals (6 + 2) * 0.75 = 6, since flat bonuses always come first
   fn priority(&self) -> i32 {
     99
   }
-  fn at_end_of_round(&self, context: &mut PowerHookContext) {
-    context.reduce_this_power();
-  }
   fn at_damage_give(
     &self,
     _context: &PowerNumericHookContext,
     damage: f64,
     damage_type: DamageType,
+    card: Option<&SingleCard>,
   ) -> f64 {
     if damage_type != DamageType::Normal {
       return damage;
@@ -371,11 +459,12 @@ impl PowerBehavior for Strength {
   fn reduce_power(&self, power: &mut Power, reduce_amount: i32) {
     self.stack_power(power, -reduce_amount);
   }
-  fn at_damage_give(
+  fn at_damage_give_flat(
     &self,
     context: &PowerNumericHookContext,
     damage: f64,
     damage_type: DamageType,
+    card: Option<&SingleCard>,
   ) -> f64 {
     if damage_type != DamageType::Normal {
       return damage;
@@ -402,7 +491,36 @@ impl PowerBehavior for Dexterity {
   }
 }
 
+// orbs read Focus directly (see `EvokeOrb` and `trigger_orb_passives`)
+impl PowerBehavior for Focus {
+  fn stack_power(&self, power: &mut Power, stack_amount: i32) {
+    Strength.stack_power(power, stack_amount)
+  }
+  fn reduce_power(&self, power: &mut Power, reduce_amount: i32) {
+    self.stack_power(power, -reduce_amount);
+  }
+}
+
+// The tick is HP loss recorded as coming from the poisoned creature itself, so it ignores block and
+// doesn't set off Thorns or on-attack effects, which only react to Normal damage from someone else.
+// The reduction is queued on top, but the damage was already computed from the unreduced amount.
+impl PowerBehavior for Poison {
+  fn at_start_of_turn(&self, context: &mut PowerHookContext) {
+    context.action_bottom(DamageAction {
+      target: context.owner_index(),
+      info: DamageInfo::new(
+        context.owner_index(),
+        context.amount(),
+        DamageType::HitpointLoss,
+      )
+      .with_source(DamageSource::Power(PowerId::Poison)),
+    });
+    context.reduce_this_power();
+  }
+}
+
 impl PowerBehavior for Thorns {
+  // `on_attacked` fires once per hit, so each hit of a multi-hit attack like Twin Strike gets its own retaliation
   fn on_attacked(&self, context: &mut PowerHookContext, info: DamageInfo, damage: i32) {
     if info.owner != context.owner_index() && info.damage_type == DamageType::Normal {
       context.action_top(DamageAction {
@@ -411,7 +529,8 @@ impl PowerBehavior for Thorns {
           context.owner_index(),
           context.amount(),
           DamageType::Thorns,
-        ),
+        )
+        .with_source(DamageSource::Power(PowerId::Thorns)),
       });
     }
   }
@@ -450,8 +569,10 @@ impl PowerBehavior for SporeCloud {
   }
 }
 
+// Applied by the Red Slaver during the monsters' turn, so it has to last through the end of that round
+// and only wear off at the end of the player's turn.
 impl PowerBehavior for Entangled {
-  fn at_end_of_round(&self, context: &mut PowerHookContext) {
+  fn at_end_of_turn(&self, context: &mut PowerHookContext) {
     context.remove_this_power();
   }
 }
@@ -473,11 +594,26 @@ impl PowerBehavior for Enrage {
 }
 
 impl PowerBehavior for Artifact {
+  // triggered by `ApplyPowerAction` instead of applying a debuff; one charge negates the whole application,
+  // however many stacks it had, and using the last charge removes the power
   fn on_specific_trigger(&self, context: &mut PowerHookContext) {
     context.reduce_this_power();
   }
 }
 
+impl PowerBehavior for SharpHide {
+  // fires when the player plays an attack, whether or not it hits this monster
+  fn on_use_card(&self, context: &mut PowerHookContext, card: &SingleCard) {
+    if card.card_info.card_type == CardType::Attack {
+      context.action_bottom(DamageAction {
+        target: CreatureIndex::Player,
+        info: DamageInfo::new(context.owner_index(), context.amount(), DamageType::Thorns)
+          .with_source(DamageSource::Power(PowerId::SharpHide)),
+      });
+    }
+  }
+}
+
 impl PowerBehavior for Metallicize {
   fn at_end_of_turn(&self, context: &mut PowerHookContext) {
     context.action_bottom(GainBlockAction {
@@ -521,6 +657,19 @@ impl PowerBehavior for NoDraw {
   }
 }
 
+// Each turn it lasts, the player draws one card fewer; `amount` is the number of turns.
+impl PowerBehavior for DrawReduction {
+  fn ticks_down(&self) -> bool {
+    true
+  }
+  fn at_end_of_round(&self, context: &mut PowerHookContext) {
+    // this runs before the tick down, so check whether the power will still be around next turn
+    if context.this_power().just_applied || context.amount() > 1 {
+      context.state_mut().next_turn_draw_modifier -= 1;
+    }
+  }
+}
+
 impl PowerBehavior for PlatedArmor {
   fn at_end_of_turn(&self, context: &mut PowerHookContext) {
     context.action_bottom(GainBlockAction {
@@ -536,6 +685,91 @@ impl PowerBehavior for PlatedArmor {
   }
 }
 
+impl PowerBehavior for Energized {
+  fn on_energy_recharge(&self, context: &mut PowerHookContext) {
+    context.state_mut().player.energy += context.amount();
+    context.remove_this_power();
+  }
+}
+
+impl PowerBehavior for Vigor {
+  // Removed only after the card's own actions, so every hit of a multi-hit attack gets the bonus,
+  // including hits whose damage is calculated when they resolve (like random-target hits).
+  fn on_after_use_card(&self, context: &mut PowerHookContext, card: &SingleCard) {
+    if card.card_info.card_type == CardType::Attack {
+      context.action_bottom(RemoveSpecificPowerAction {
+        target: context.owner_index(),
+        power_id: PowerId::Vigor,
+      });
+    }
+  }
+  fn at_damage_give_flat(
+    &self,
+    context: &PowerNumericHookContext,
+    damage: f64,
+    damage_type: DamageType,
+    card: Option<&SingleCard>,
+  ) -> f64 {
+    if damage_type != DamageType::Normal {
+      return damage;
+    }
+    damage + context.amount() as f64
+  }
+}
+
+impl PowerBehavior for Calipers {
+  fn modify_block_retention(&self, _context: &PowerNumericHookContext, block: i32, retained: i32) -> i32 {
+    std::cmp::max(retained, block - 15)
+  }
+}
+
+impl PowerBehavior for Boot {
+  fn on_attack_to_change_damage(&self, context: &PowerNumericHookContext, info: &DamageInfo, damage: i32) -> i32 {
+    if info.damage_type == DamageType::Normal && damage > 0 && damage < 5 {
+      5
+    } else {
+      damage
+    }
+  }
+}
+
+impl PowerBehavior for StrikeDummy {
+  fn at_damage_give_flat(
+    &self,
+    context: &PowerNumericHookContext,
+    damage: f64,
+    damage_type: DamageType,
+    card: Option<&SingleCard>,
+  ) -> f64 {
+    match card {
+      Some(card) if card.card_info.strike && damage_type == DamageType::Normal => damage + 3.0,
+      _ => damage,
+    }
+  }
+}
+
+impl PowerBehavior for WristBlade {
+  fn at_damage_give_flat(
+    &self,
+    context: &PowerNumericHookContext,
+    damage: f64,
+    damage_type: DamageType,
+    card: Option<&SingleCard>,
+  ) -> f64 {
+    match card {
+      // uses the cost actually paid, so cards reduced to 0 by other effects count too
+      Some(card)
+        if card.card_info.card_type == CardType::Attack
+          && context.state().effective_cost(card) == 0
+          && damage_type == DamageType::Normal =>
+      {
+        damage + 4.0
+      }
+      _ => damage,
+    }
+  }
+}
+
 macro_rules! energy_relic {
   () => {
     fn inherent_energy(&self) -> i32 {
@@ -569,11 +803,46 @@ impl PowerBehavior for Sozu {
   energy_relic!{}
 }
 
+impl PowerBehavior for PenNibRelic {
+  // amount is the relic's counter; misc is set while the 10th attack is being played
+  fn priority(&self) -> i32 {
+    6
+  }
+  fn on_use_card(&self, context: &mut PowerHookContext, card: &SingleCard) {
+    if card.card_info.card_type == CardType::Attack {
+      // if the game already gave us the Pen Nib power, it does the doubling instead
+      let has_power = context.owner_creature().has_power(PowerId::PenNib);
+      let relic = context.this_power_mut();
+      relic.amount += 1;
+      if relic.amount >= 10 {
+        relic.amount = 0;
+        relic.misc = if has_power { 0 } else { 1 };
+      }
+    }
+  }
+  fn on_after_use_card(&self, context: &mut PowerHookContext, card: &SingleCard) {
+    context.this_power_mut().misc = 0;
+  }
+  fn at_damage_give(
+    &self,
+    context: &PowerNumericHookContext,
+    damage: f64,
+    damage_type: DamageType,
+    card: Option<&SingleCard>,
+  ) -> f64 {
+    let attack = card.map_or(false, |card| card.card_info.card_type == CardType::Attack);
+    if context.this_power().misc == 0 || damage_type != DamageType::Normal || !attack {
+      return damage;
+    }
+    damage * 2.0
+  }
+}
+
 impl PowerBehavior for PenNib {
   fn priority(&self) -> i32 {
     6
   }
-  fn on_use_card (&self, context: &mut PowerHookContext, card: &SingleCard) {
+  fn on_use_card(&self, context: &mut PowerHookContext, card: &SingleCard) {
     if card.card_info.card_type == CardType::Attack {
       context.remove_this_power();
     }
@@ -583,6 +852,7 @@ impl PowerBehavior for PenNib {
     _context: &PowerNumericHookContext,
     damage: f64,
     damage_type: DamageType,
+    card: Option<&SingleCard>,
   ) -> f64 {
     if damage_type != DamageType::Normal {
       return damage;
@@ -596,7 +866,7 @@ impl PowerBehavior for Combust {
 }
 
 impl PowerBehavior for Corruption {
-  //TODO
+  // skills cost 0 (see `CombatState::effective_cost`) and exhaust when played (see `FinishPlayingCard`)
 }
 
 impl PowerBehavior for Evolve {
@@ -644,19 +914,46 @@ impl PowerBehavior for Rage {
 }
 
 impl PowerBehavior for Rupture {
-  //TODO
+  fn on_attacked(&self, context: &mut PowerHookContext, info: DamageInfo, damage: i32) {
+    // HP loss from the owner's cards (Hemokinesis) or own buffs (Brutality) counts, but not from a debuff
+    // like Poison, which is recorded as coming from its victim even though someone else applied it
+    let self_inflicted = match info.source {
+      Some(DamageSource::Card(_)) => true,
+      Some(DamageSource::Power(power_id)) => power_id.power_type() != Debuff,
+      _ => false,
+    };
+    if damage > 0 && info.owner == context.owner_index() && self_inflicted {
+      context.power_owner_top(PowerId::Strength, context.amount());
+    }
+  }
 }
 
-impl PowerBehavior for Barricade {}
+impl PowerBehavior for Barricade {
+  fn modify_block_retention(&self, _context: &PowerNumericHookContext, block: i32, _retained: i32) -> i32 {
+    block
+  }
+}
 
 impl PowerBehavior for Berserk {
-  fn at_start_of_turn(&self, context: &mut PowerHookContext) {
-    context.action_bottom(GainEnergyAction(context.amount()))
+  fn on_energy_recharge(&self, context: &mut PowerHookContext) {
+    context.state_mut().player.energy += context.amount();
   }
 }
 
 impl PowerBehavior for Brutality {
-  //TODO
+  fn at_start_of_turn_post_draw(&self, context: &mut PowerHookContext) {
+    // if the HP loss is lethal, combat ends before the draw gets to happen
+    context.action_bottom(DamageAction {
+      target: context.owner_index(),
+      info: DamageInfo::new(
+        context.owner_index(),
+        context.amount(),
+        DamageType::HitpointLoss,
+      )
+      .with_source(DamageSource::Power(PowerId::Brutality)),
+    });
+    context.action_bottom(DrawCards(context.amount()));
+  }
 }
 
 impl PowerBehavior for DarkEmbrace {
@@ -666,22 +963,675 @@ impl PowerBehavior for DarkEmbrace {
 }
 
 impl PowerBehavior for DemonForm {
-  fn at_start_of_turn_post_draw(&self, context: &mut PowerHookContext) {
-    context.power_owner_bottom(PowerId::Strength, context.amount());
+  fn at_start_of_turn(&self, context: &mut PowerHookContext) {
+    // applied immediately rather than queued, so that the Strength is already there for the whole turn
+    context.action_now(&ApplyPowerAction {
+      source: context.owner_index(),
+      target: context.owner_index(),
+      power_id: PowerId::Strength,
+      amount: context.amount(),
+    });
   }
 }
 
-impl PowerBehavior for DoubleTap {
-  //TODO
-}
+// Double Tap, Burst and Amplify are converted to `CombatState::repeat_next` when reading the game state
+impl PowerBehavior for DoubleTap {}
 
 impl PowerBehavior for Juggernaut {
   fn on_gained_block(&self, context: &mut PowerHookContext, block: f64) {
-    //TODO
+    // Thorns damage doesn't give block back, so this can't trigger itself
+    if block > 0.0 {
+      context.action_top(DamageRandomEnemyAction {
+        damage: context.amount(),
+        damage_type: DamageType::Thorns,
+      });
+    }
   }
 }
 
+impl PowerBehavior for ToolsOfTheTrade {
+  fn at_start_of_turn_post_draw(&self, context: &mut PowerHookContext) {
+    context.action_bottom(DrawCards(context.amount()));
+    context.action_bottom(ChooseCardsAction::new(CardChoiceType::DiscardCard, context.amount()));
+  }
+}
+
+impl PowerBehavior for SadisticNature {
+  fn on_debuff_applied(&self, context: &mut PowerHookContext, target: CreatureIndex, power_id: PowerId) {
+    context.action_bottom(DamageAction {
+      target,
+      info: DamageInfo::new(context.owner_index(), context.amount(), DamageType::Thorns)
+        .with_source(DamageSource::Power(PowerId::SadisticNature)),
+    });
+  }
+}
+
+impl PowerBehavior for Envenom {
+  fn on_attack(&self, context: &mut PowerHookContext, info: DamageInfo, damage: i32, target: CreatureIndex) {
+    // called once per hit, so each unblocked hit of a multi-hit attack applies its own Poison
+    if damage > 0 && target != context.owner_index() && info.damage_type == DamageType::Normal {
+      context.action_top(ApplyPowerAction {
+        source: context.owner_index(),
+        target,
+        power_id: PowerId::Poison,
+        amount: context.amount(),
+      });
+    }
+  }
+}
+
+impl PowerBehavior for NoxiousFumes {
+  fn at_start_of_turn_post_draw(&self, context: &mut PowerHookContext) {
+    // monsters that spawned during the previous turn are included, since this checks the monster list each turn
+    for monster_index in 0..context.state().monsters.len() {
+      if !context.state().monsters[monster_index].gone {
+        context.action_bottom(ApplyPowerAction {
+          source: context.owner_index(),
+          target: CreatureIndex::Monster(monster_index),
+          power_id: PowerId::Poison,
+          amount: context.amount(),
+        });
+      }
+    }
+  }
+}
+
+impl PowerBehavior for Burst {}
 
+impl PowerBehavior for Accuracy {
+  fn at_damage_give_flat(
+    &self,
+    context: &PowerNumericHookContext,
+    damage: f64,
+    damage_type: DamageType,
+    card: Option<&SingleCard>,
+  ) -> f64 {
+    match card {
+      Some(card) if card.card_info.id == CardId::Shiv && damage_type == DamageType::Normal => {
+        damage + context.amount() as f64
+      }
+      _ => damage,
+    }
+  }
+}
+
+impl PowerBehavior for Amplify {}
+
+// `FinishPlayingCard` puts the next card on top of the draw pile and uses this up
+impl PowerBehavior for Rebound {
+  fn at_end_of_turn(&self, context: &mut PowerHookContext) {
+    context.remove_this_power();
+  }
+}
+
+// handled in `DamageAction`, once block and the damage hooks have had their say
+impl PowerBehavior for Buffer {}
+
+impl PowerBehavior for Establishment {
+  fn at_end_of_turn(&self, context: &mut PowerHookContext) {
+    // the cost reduction sticks to the card for the rest of combat
+    let amount = context.amount();
+    for card in &mut context.state_mut().hand {
+      if card.card_info.retain && card.cost > 0 {
+        card.cost = std::cmp::max(0, card.cost - amount);
+      }
+    }
+  }
+}
+
+impl PowerBehavior for Panache {
+  // misc counts the cards played this turn since the last trigger
+  fn on_use_card(&self, context: &mut PowerHookContext, card: &SingleCard) {
+    let power = context.this_power_mut();
+    power.misc += 1;
+    if power.misc >= 5 {
+      power.misc = 0;
+      context.action_bottom(DamageAllEnemiesAction {
+        damage_type: DamageType::Thorns,
+        damage: context.amount(),
+      });
+    }
+  }
+  fn at_start_of_turn(&self, context: &mut PowerHookContext) {
+    context.this_power_mut().misc = 0;
+  }
+}
+
+// the cap to 1 is applied in `DamageAction`
+impl PowerBehavior for IntangiblePlayer {
+  fn ticks_down(&self) -> bool {
+    true
+  }
+}
+
+impl PowerBehavior for PainfulStabs {
+  fn on_attack(&self, context: &mut PowerHookContext, info: DamageInfo, damage: i32, target: CreatureIndex) {
+    if damage > 0 && target == CreatureIndex::Player && info.damage_type == DamageType::Normal {
+      context.action_bottom(DiscardNewCard(SingleCard::create(CardId::Wound)));
+    }
+  }
+}
+
+impl PowerBehavior for Reactive {
+  fn on_attacked(&self, context: &mut PowerHookContext, info: DamageInfo, damage: i32) {
+    if damage > 0 && info.damage_type == DamageType::Normal {
+      // read by `WrithingMass::make_intent_distribution`, which clears it again
+      context.this_power_mut().misc = 1;
+    }
+  }
+}
+
+impl PowerBehavior for Malleable {
+  fn on_attacked(&self, context: &mut PowerHookContext, info: DamageInfo, damage: i32) {
+    let hitpoints = context.state().get_creature(context.owner_index()).hitpoints;
+    if damage > 0 && damage < hitpoints && info.damage_type == DamageType::Normal {
+      context.action_bottom(GainBlockAction {
+        creature_index: context.owner_index(),
+        amount: context.amount(),
+      });
+      context.this_power_mut().amount += 1;
+    }
+  }
+  fn at_end_of_round(&self, context: &mut PowerHookContext) {
+    // Writhing Mass, the only monster with Malleable, always starts at 3
+    context.this_power_mut().amount = 3;
+  }
+}
+
+// the cap to 1 is applied in `DamageAction`
+impl PowerBehavior for Intangible {
+  fn ticks_down(&self) -> bool {
+    true
+  }
+}
+
+impl PowerBehavior for TimeWarp {
+  // amount counts the cards played since it last triggered, across turns, like the game's counter
+  fn on_after_use_card(&self, context: &mut PowerHookContext, card: &SingleCard) {
+    let power = context.this_power_mut();
+    power.amount += 1;
+    if power.amount >= 12 {
+      power.amount = 0;
+      for monster_index in 0..context.state().monsters.len() {
+        if !context.state().monsters[monster_index].gone {
+          context.action_bottom(ApplyPowerAction {
+            source: context.owner_index(),
+            target: CreatureIndex::Monster(monster_index),
+            power_id: PowerId::Strength,
+            amount: 2,
+          });
+        }
+      }
+      // this runs after the card's own actions were queued, so the card that triggered it still resolves first
+      context.action_bottom(EndTurn);
+    }
+  }
+}
 
 impl PowerBehavior for Split {}
 impl PowerBehavior for Unknown {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::simulation_state::testing::*;
+
+  #[test]
+  fn poison_loses_its_amount_and_then_one_each_turn() {
+    let mut poisoned = monster(MonsterId::Cultist, 50, vec![3]);
+    poisoned.creature.powers.push(power(PowerId::Poison, 5));
+    let mut state = combat(vec![poisoned], Vec::new());
+    for _ in 0..3 {
+      end_turn(&mut state);
+    }
+    assert_eq!(state.monsters[0].creature.hitpoints, 50 - (5 + 4 + 3));
+    assert_eq!(state.monsters[0].creature.power_amount(PowerId::Poison), 2);
+  }
+
+  #[test]
+  fn a_monster_killed_by_poison_does_not_get_to_attack() {
+    let mut poisoned = monster(MonsterId::Cultist, 5, vec![3, 1]);
+    poisoned.creature.powers.push(power(PowerId::Poison, 5));
+    let mut state = combat(vec![poisoned, monster(MonsterId::Cultist, 50, vec![3])], Vec::new());
+    end_turn(&mut state);
+    assert!(state.monsters[0].gone);
+    assert_eq!(state.player.creature.hitpoints, 80);
+    assert!(!state.combat_over());
+  }
+
+  #[test]
+  fn poison_is_removed_once_it_runs_out() {
+    let mut poisoned = monster(MonsterId::Cultist, 50, vec![3]);
+    poisoned.creature.powers.push(power(PowerId::Poison, 1));
+    let mut state = combat(vec![poisoned], Vec::new());
+    end_turn(&mut state);
+    assert_eq!(state.monsters[0].creature.hitpoints, 49);
+    assert!(!state.monsters[0].creature.has_power(PowerId::Poison));
+  }
+
+  #[test]
+  fn rupture_gives_strength_for_self_inflicted_hp_loss_but_not_poison() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![3])],
+      vec![card(CardId::Hemokinesis)],
+    );
+    state.player.creature.powers.push(power(PowerId::Rupture, 1));
+    play(&mut state, CardId::Hemokinesis, 0);
+    assert_eq!(state.player.creature.hitpoints, 78);
+    assert!(state.monsters[0].creature.hitpoints <= 50 - 14);
+    assert_eq!(state.player.creature.power_amount(PowerId::Strength), 1);
+
+    state.player.creature.powers.push(power(PowerId::Poison, 3));
+    end_turn(&mut state);
+    assert_eq!(state.player.creature.hitpoints, 78 - 3);
+    assert_eq!(state.player.creature.power_amount(PowerId::Strength), 1);
+  }
+
+  #[test]
+  fn frail_reduces_the_block_from_defend() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![3])],
+      vec![card(CardId::DefendR)],
+    );
+    state.player.creature.powers.push(power(PowerId::Frail, 1));
+    play(&mut state, CardId::DefendR, 0);
+    // floor(5 * 0.75)
+    assert_eq!(state.player.creature.block, 3);
+  }
+
+  #[test]
+  fn artifact_negates_a_debuff_but_not_a_buff() {
+    let mut shielded = monster(MonsterId::Cultist, 50, vec![3]);
+    shielded.creature.powers.push(power(PowerId::Artifact, 1));
+    let mut state = combat(vec![shielded], Vec::new());
+    run(
+      &mut state,
+      &ApplyPowerAction {
+        source: CreatureIndex::Player,
+        target: CreatureIndex::Monster(0),
+        power_id: PowerId::Weak,
+        amount: 2,
+      },
+    );
+    assert!(!state.monsters[0].creature.has_power(PowerId::Weak));
+    assert!(!state.monsters[0].creature.has_power(PowerId::Artifact));
+
+    state.player.creature.powers.push(power(PowerId::Artifact, 1));
+    run(
+      &mut state,
+      &ApplyPowerAction {
+        source: CreatureIndex::Player,
+        target: CreatureIndex::Player,
+        power_id: PowerId::Strength,
+        amount: 2,
+      },
+    );
+    assert_eq!(state.player.creature.power_amount(PowerId::Strength), 2);
+    assert_eq!(state.player.creature.power_amount(PowerId::Artifact), 1);
+  }
+
+  #[test]
+  fn thorns_retaliates_against_each_hit() {
+    let mut spiky = monster(MonsterId::Cultist, 50, vec![3]);
+    spiky.creature.powers.push(power(PowerId::Thorns, 3));
+    let mut state = combat(vec![spiky], vec![card(CardId::TwinStrike)]);
+    play(&mut state, CardId::TwinStrike, 0);
+    assert_eq!(state.monsters[0].creature.hitpoints, 50 - 2 * 5);
+    assert_eq!(state.player.creature.hitpoints, 80 - 2 * 3);
+  }
+
+  #[test]
+  fn weak_reduces_a_strike_to_4() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![3])],
+      vec![card(CardId::StrikeR)],
+    );
+    state.player.creature.powers.push(power(PowerId::Weak, 1));
+    play(&mut state, CardId::StrikeR, 0);
+    // floor(6 * 0.75)
+    assert_eq!(state.monsters[0].creature.hitpoints, 50 - 4);
+  }
+
+  #[test]
+  fn time_warp_ends_the_turn_after_the_twelfth_card_resolves() {
+    let mut time_eater = monster(MonsterId::Cultist, 50, vec![1]);
+    time_eater.creature.powers.push(power(PowerId::TimeWarp, 11));
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 6, vec![1]), time_eater],
+      vec![card(CardId::StrikeR)],
+    );
+    play(&mut state, CardId::StrikeR, 0);
+
+    // the Strike killed the first Cultist before the monsters' turn, so only the other one attacked
+    assert!(state.monsters[0].gone);
+    assert_eq!(state.turn_number, 1);
+    assert_eq!(state.player.creature.hitpoints, 80 - (6 + 2));
+    assert_eq!(state.monsters[1].creature.power_amount(PowerId::TimeWarp), 0);
+  }
+
+  #[test]
+  fn berserk_gives_an_extra_energy_each_turn() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![3])],
+      vec![card(CardId::Berserk)],
+    );
+    play(&mut state, CardId::Berserk, 0);
+    assert_eq!(state.player.energy, 3);
+    assert_eq!(state.player.creature.power_amount(PowerId::Vulnerable), 1);
+    end_turn(&mut state);
+    assert_eq!(state.player.energy, 4);
+  }
+
+  #[test]
+  fn demon_form_stacks_and_adds_strength_every_turn() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![3])],
+      vec![card(CardId::DemonForm), card(CardId::DemonForm)],
+    );
+    state.player.energy = 6;
+    play(&mut state, CardId::DemonForm, 0);
+    play(&mut state, CardId::DemonForm, 0);
+    assert_eq!(state.player.creature.power_amount(PowerId::DemonForm), 6);
+    assert_eq!(state.player.creature.power_amount(PowerId::Strength), 0);
+    end_turn(&mut state);
+    assert_eq!(state.player.creature.power_amount(PowerId::Strength), 6);
+    end_turn(&mut state);
+    assert_eq!(state.player.creature.power_amount(PowerId::Strength), 12);
+  }
+
+  #[test]
+  fn demon_form_applies_its_strength_before_the_draw() {
+    let mut state = combat(vec![monster(MonsterId::Cultist, 50, vec![3])], Vec::new());
+    state.draw_pile = (0..5).map(|_| card(CardId::StrikeR)).collect();
+    state.player.creature.powers.push(power(PowerId::DemonForm, 3));
+    let mut strength_when_drawing = Vec::new();
+    let mut observer = |state: &CombatState, action: &DynAction| {
+      if let DynAction::DrawCards(_) = action {
+        strength_when_drawing.push(state.player.creature.power_amount(PowerId::Strength));
+      }
+    };
+    let mut runner = Runner::with_seed(&mut state, true, false, 0).with_step_observer(&mut observer);
+    runner.action_now(&EndTurn);
+    run_until_unable(&mut runner);
+    drop(runner);
+    assert_eq!(strength_when_drawing, vec![3]);
+    assert_eq!(state.hand.len(), 5);
+  }
+
+  #[test]
+  fn brutality_loses_hitpoints_and_draws_unless_that_is_lethal() {
+    let mut state = combat(vec![monster(MonsterId::Cultist, 50, vec![3])], Vec::new());
+    state.draw_pile = (0..7).map(|_| card(CardId::StrikeR)).collect();
+    state.player.creature.powers.push(power(PowerId::Brutality, 1));
+    let mut dying = state.clone();
+    end_turn(&mut state);
+    assert_eq!(state.player.creature.hitpoints, 79);
+    assert_eq!(state.hand.len(), 6);
+
+    dying.player.creature.hitpoints = 1;
+    end_turn(&mut dying);
+    assert!(dying.combat_over());
+    assert_eq!(dying.player.creature.hitpoints, 0);
+    assert_eq!(dying.hand.len(), 5);
+  }
+
+  #[test]
+  fn exhausting_a_skill_under_corruption_triggers_dark_embrace_and_feel_no_pain() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![3])],
+      vec![card(CardId::DefendR)],
+    );
+    state.draw_pile = vec![card(CardId::StrikeR)];
+    state.player.creature.powers.push(power(PowerId::Corruption, -1));
+    state.player.creature.powers.push(power(PowerId::DarkEmbrace, 1));
+    state.player.creature.powers.push(power(PowerId::FeelNoPain, 3));
+    play(&mut state, CardId::DefendR, 0);
+    assert_eq!(state.player.energy, 3);
+    assert_eq!(state.exhaust_pile, vec![card(CardId::DefendR)]);
+    assert!(state.discard_pile.is_empty());
+    assert_eq!(state.hand.to_vec(), vec![card(CardId::StrikeR)]);
+    assert_eq!(state.player.creature.block, 5 + 3);
+  }
+
+  #[test]
+  fn panache_hits_every_enemy_on_the_fifth_card() {
+    let mut state = combat(
+      vec![
+        monster(MonsterId::Cultist, 50, vec![3]),
+        monster(MonsterId::Cultist, 40, vec![3]),
+      ],
+      (0..5).map(|_| card(CardId::DefendR)).collect(),
+    );
+    state.player.energy = 5;
+    state.player.creature.powers.push(power(PowerId::Panache, 10));
+    for _ in 0..4 {
+      play(&mut state, CardId::DefendR, 0);
+    }
+    assert_eq!(state.monsters[0].creature.hitpoints, 50);
+    assert_eq!(state.monsters[1].creature.hitpoints, 40);
+    play(&mut state, CardId::DefendR, 0);
+    assert_eq!(state.monsters[0].creature.hitpoints, 40);
+    assert_eq!(state.monsters[1].creature.hitpoints, 30);
+    assert_eq!(state.player.creature.powers[0].misc, 0);
+  }
+
+  #[test]
+  fn vigor_boosts_one_attack_and_then_is_gone() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 100, vec![3])],
+      vec![card(CardId::StrikeR), card(CardId::TwinStrike)],
+    );
+    state.player.creature.powers.push(power(PowerId::Vigor, 8));
+    play(&mut state, CardId::StrikeR, 0);
+    assert_eq!(state.monsters[0].creature.hitpoints, 100 - 14);
+    assert!(!state.player.creature.has_power(PowerId::Vigor));
+
+    state.player.creature.powers.push(power(PowerId::Vigor, 8));
+    play(&mut state, CardId::TwinStrike, 0);
+    assert_eq!(state.monsters[0].creature.hitpoints, 100 - 14 - 2 * 13);
+    assert!(!state.player.creature.has_power(PowerId::Vigor));
+  }
+
+  #[test]
+  fn vigor_boosts_every_hit_of_whirlwind() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 100, vec![3])],
+      vec![card(CardId::Whirlwind)],
+    );
+    state.player.creature.powers.push(power(PowerId::Vigor, 5));
+    play(&mut state, CardId::Whirlwind, 0);
+    assert_eq!(state.player.energy, 0);
+    assert_eq!(state.monsters[0].creature.hitpoints, 100 - 3 * (5 + 5));
+    assert!(!state.player.creature.has_power(PowerId::Vigor));
+  }
+
+  #[test]
+  fn sadistic_nature_deals_damage_for_each_debuff_that_sticks() {
+    let mut shielded = monster(MonsterId::Cultist, 50, vec![3]);
+    shielded.creature.powers.push(power(PowerId::Artifact, 1));
+    let mut state = combat(vec![monster(MonsterId::Cultist, 50, vec![3]), shielded], Vec::new());
+    state.player.creature.powers.push(power(PowerId::SadisticNature, 5));
+    for target in 0..2 {
+      run(
+        &mut state,
+        &ApplyPowerAction {
+          source: CreatureIndex::Player,
+          target: CreatureIndex::Monster(target),
+          power_id: PowerId::Vulnerable,
+          amount: 2,
+        },
+      );
+    }
+    assert_eq!(state.monsters[0].creature.hitpoints, 45);
+    assert_eq!(state.monsters[1].creature.hitpoints, 50);
+    assert!(!state.monsters[1].creature.has_power(PowerId::Vulnerable));
+    assert!(!state.monsters[1].creature.has_power(PowerId::Artifact));
+  }
+
+  #[test]
+  fn envenom_poisons_once_per_unblocked_hit() {
+    let mut blocking = monster(MonsterId::Cultist, 50, vec![3]);
+    blocking.creature.block = 20;
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![3]), blocking],
+      vec![card(CardId::TwinStrike), card(CardId::TwinStrike)],
+    );
+    state.player.creature.powers.push(power(PowerId::Envenom, 1));
+    play(&mut state, CardId::TwinStrike, 0);
+    assert_eq!(state.monsters[0].creature.power_amount(PowerId::Poison), 2);
+    play(&mut state, CardId::TwinStrike, 1);
+    assert_eq!(state.monsters[1].creature.block, 10);
+    assert!(!state.monsters[1].creature.has_power(PowerId::Poison));
+  }
+
+  #[test]
+  fn noxious_fumes_poisons_every_monster_each_turn() {
+    let mut state = combat(
+      vec![
+        monster(MonsterId::Cultist, 50, vec![3]),
+        monster(MonsterId::Cultist, 40, vec![3]),
+      ],
+      Vec::new(),
+    );
+    state.player.creature.powers.push(power(PowerId::NoxiousFumes, 2));
+    end_turn(&mut state);
+    for monster in &state.monsters {
+      assert_eq!(monster.creature.power_amount(PowerId::Poison), 2);
+    }
+    end_turn(&mut state);
+    // the first 2 Poison ticked once at the start of the monsters' turn, then 2 more were applied
+    assert_eq!(state.monsters[0].creature.hitpoints, 48);
+    assert_eq!(state.monsters[1].creature.hitpoints, 38);
+    for monster in &state.monsters {
+      assert_eq!(monster.creature.power_amount(PowerId::Poison), 1 + 2);
+    }
+  }
+
+  #[test]
+  fn accuracy_boosts_shivs_but_not_strikes() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![3])],
+      vec![card(CardId::Shiv), card(CardId::StrikeR)],
+    );
+    state.player.creature.powers.push(power(PowerId::Accuracy, 4));
+    play(&mut state, CardId::Shiv, 0);
+    assert_eq!(state.monsters[0].creature.hitpoints, 50 - 8);
+    play(&mut state, CardId::StrikeR, 0);
+    assert_eq!(state.monsters[0].creature.hitpoints, 50 - 8 - 6);
+  }
+
+  #[test]
+  fn pen_nib_doubles_every_tenth_attack() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![3])],
+      vec![card(CardId::StrikeR), card(CardId::StrikeR)],
+    );
+    state.player.creature.powers.push(power(PowerId::PenNibRelic, 8));
+    play(&mut state, CardId::StrikeR, 0);
+    assert_eq!(state.monsters[0].creature.hitpoints, 50 - 6);
+    play(&mut state, CardId::StrikeR, 0);
+    assert_eq!(state.monsters[0].creature.hitpoints, 50 - 6 - 12);
+    assert_eq!(state.player.creature.power_amount(PowerId::PenNibRelic), 0);
+  }
+
+  #[test]
+  fn the_boot_raises_small_unblocked_damage_to_5() {
+    let mut blocking = monster(MonsterId::Cultist, 50, vec![3]);
+    blocking.creature.block = 3;
+    let mut state = combat(vec![blocking], vec![card(CardId::StrikeR)]);
+    state.player.creature.powers.push(power(PowerId::Boot, -1));
+    play(&mut state, CardId::StrikeR, 0);
+    assert_eq!(state.monsters[0].creature.block, 0);
+    assert_eq!(state.monsters[0].creature.hitpoints, 50 - 5);
+  }
+
+  #[test]
+  fn strike_dummy_only_boosts_strikes() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![3])],
+      vec![card(CardId::StrikeR), card(CardId::Anger)],
+    );
+    state.player.creature.powers.push(power(PowerId::StrikeDummy, -1));
+    play(&mut state, CardId::StrikeR, 0);
+    assert_eq!(state.monsters[0].creature.hitpoints, 50 - 9);
+    play(&mut state, CardId::Anger, 0);
+    assert_eq!(state.monsters[0].creature.hitpoints, 50 - 9 - 6);
+  }
+
+  #[test]
+  fn wrist_blade_only_boosts_0_cost_attacks() {
+    let mut state = combat(
+      vec![monster(MonsterId::Cultist, 50, vec![3])],
+      vec![card(CardId::Anger), card(CardId::StrikeR)],
+    );
+    state.player.creature.powers.push(power(PowerId::WristBlade, -1));
+    play(&mut state, CardId::Anger, 0);
+    assert_eq!(state.monsters[0].creature.hitpoints, 50 - 10);
+    play(&mut state, CardId::StrikeR, 0);
+    assert_eq!(state.monsters[0].creature.hitpoints, 50 - 10 - 6);
+  }
+
+  #[test]
+  fn sharp_hide_retaliates_against_attacks_but_not_skills() {
+    let mut guardian = monster(MonsterId::TheGuardian, 240, vec![1]);
+    guardian.creature.powers.push(power(PowerId::SharpHide, 3));
+    let mut state = combat(vec![guardian], vec![card(CardId::StrikeR), card(CardId::DefendR)]);
+    play(&mut state, CardId::DefendR, 0);
+    assert_eq!(state.player.creature.hitpoints, 80);
+    assert_eq!(state.player.creature.block, 5);
+    play(&mut state, CardId::StrikeR, 0);
+    assert_eq!(state.player.creature.block, 2);
+    assert_eq!(state.player.creature.hitpoints, 80);
+    state.player.creature.block = 0;
+    state.hand.push(card(CardId::StrikeR));
+    play(&mut state, CardId::StrikeR, 0);
+    assert_eq!(state.player.creature.hitpoints, 77);
+  }
+
+  #[test]
+  fn painful_stabs_adds_a_wound_when_the_monster_hits() {
+    let mut stabbing = monster(MonsterId::Cultist, 50, vec![1]);
+    stabbing.creature.powers.push(power(PowerId::PainfulStabs, -1));
+    let mut state = combat(vec![stabbing], Vec::new());
+    state.draw_pile = (0..5).map(|_| card(CardId::StrikeR)).collect();
+    end_turn(&mut state);
+    assert_eq!(state.player.creature.hitpoints, 74);
+    assert_eq!(state.discard_pile, vec![card(CardId::Wound)]);
+
+    // nothing is added when the attack is fully blocked
+    state.discard_pile.clear();
+    state.player.creature.block = 20;
+    state.draw_pile = (0..5).map(|_| card(CardId::StrikeR)).collect();
+    end_turn(&mut state);
+    assert!(state.discard_pile.iter().all(|card| card.card_info.id != CardId::Wound));
+  }
+
+  #[test]
+  fn power_metadata_says_which_powers_tick_down() {
+    assert!(PowerId::Vulnerable.ticks_down());
+    assert!(PowerId::Weak.ticks_down());
+    assert!(!PowerId::Strength.ticks_down());
+    assert!(!PowerId::Poison.ticks_down());
+    assert_eq!(PowerId::Vulnerable.power_type(), Debuff);
+    assert_eq!(PowerId::Strength.power_type(), Buff);
+    assert_eq!(PowerId::PenNibRelic.power_type(), Relic);
+  }
+
+  #[test]
+  fn barricade_keeps_all_block_and_calipers_keeps_all_but_15() {
+    let mut state = combat(vec![monster(MonsterId::Cultist, 50, vec![3])], Vec::new());
+    let mut calipers = state.clone();
+    state.player.creature.powers.push(power(PowerId::Barricade, -1));
+    state.player.creature.block = 12;
+    end_turn(&mut state);
+    assert_eq!(state.player.creature.block, 12);
+
+    calipers.player.creature.powers.push(power(PowerId::Calipers, -1));
+    calipers.player.creature.block = 20;
+    end_turn(&mut calipers);
+    assert_eq!(calipers.player.creature.block, 5);
+    calipers.player.creature.block = 10;
+    end_turn(&mut calipers);
+    assert_eq!(calipers.player.creature.block, 0);
+  }
+}
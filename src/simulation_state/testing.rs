@@ -0,0 +1,106 @@
+//! Small hand-built combats for unit tests, so tests don't need a state read from the game.
+
+use std::collections::VecDeque;
+
+use crate::actions::*;
+use crate::simulation::*;
+use crate::simulation_state::*;
+
+pub fn card(id: CardId) -> SingleCard {
+  SingleCard::create(id)
+}
+
+pub fn upgraded(id: CardId) -> SingleCard {
+  let mut result = SingleCard::create(id);
+  result.upgrade();
+  result
+}
+
+pub fn power(power_id: PowerId, amount: i32) -> Power {
+  Power {
+    power_id,
+    amount,
+    ..Power::default()
+  }
+}
+
+/// A monster at ascension 0 with full hitpoints; `move_history` needs at least the current intent.
+pub fn monster(monster_id: MonsterId, hitpoints: i32, move_history: Vec<i32>) -> Monster {
+  Monster {
+    monster_id,
+    innate_damage_amount: None,
+    ascension: 0,
+    creature: Creature {
+      hitpoints,
+      max_hitpoints: hitpoints,
+      block: 0,
+      powers: Vec::new(),
+    },
+    move_history,
+    gone: false,
+    intent_cache: None,
+  }
+}
+
+/// The first turn of a combat against `monsters`, with `hand` in hand, 3 energy, 80/80 hitpoints and
+/// every other pile empty.
+pub fn combat(monsters: Vec<Monster>, hand: Vec<SingleCard>) -> CombatState {
+  let mut result = CombatState {
+    draw_pile: Vec::new(),
+    discard_pile: Vec::new(),
+    exhaust_pile: Vec::new(),
+    hand: hand.into_iter().collect(),
+    limbo: Vec::new(),
+    card_in_play: None,
+    player: Player {
+      creature: Creature {
+        hitpoints: 80,
+        max_hitpoints: 80,
+        block: 0,
+        powers: Vec::new(),
+      },
+      energy: 3,
+      stance: Stance::Neutral,
+      orbs: Vec::new(),
+      orb_slots: 0,
+    },
+    monsters: monsters.into_iter().collect(),
+    turn_number: 0,
+    turn_has_ended: false,
+    cached_combat_over: false,
+    draw_pile_known_top: 0,
+    next_turn_draw_modifier: 0,
+    delayed_actions: Vec::new(),
+    repeat_next: Vec::new(),
+    starting_gold: 0,
+    gold_gained: 0,
+    relics: Vec::new(),
+    fresh_subaction_queue: Vec::new(),
+    stale_subaction_stack: Vec::new(),
+    actions: VecDeque::new(),
+  };
+  result.update_combat_over();
+  result
+}
+
+/// Applies `action` and runs until the next choice, with randomness from a fixed seed.
+pub fn run(state: &mut CombatState, action: &impl Action) {
+  let mut runner = Runner::with_seed(state, true, false, 0);
+  runner.action_now(action);
+  run_until_unable(&mut runner);
+}
+
+/// Plays the first card in hand with this id.
+pub fn play(state: &mut CombatState, id: CardId, target: usize) {
+  let card = state
+    .hand
+    .iter()
+    .find(|card| card.card_info.id == id)
+    .expect("played a card that isn't in hand")
+    .clone();
+  run(state, &PlayCard { card, target });
+}
+
+pub fn end_turn(state: &mut CombatState) {
+  run(state, &EndTurn);
+}
@@ -1,16 +1,18 @@
 //use arrayvec::ArrayVec;
 use ordered_float::OrderedFloat;
-use rand::{seq::SliceRandom};
+use rand::{seq::SliceRandom, Rng};
+use rand_xoshiro::Xoshiro256StarStar;
 use array_ext::Array;
 use enum_map::EnumMap;
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use crate::actions::*;
 use crate::simulation::*;
 use crate::simulation_state::*;
 
 pub trait Strategy {
-  fn choose_choice(&self, state: &CombatState) -> Vec<Choice>;
+  /// Any randomness the strategy uses should come from `rng`, so that playouts are reproducible from their seed.
+  fn choose_choice(&self, state: &CombatState, rng: &mut Xoshiro256StarStar) -> Vec<Choice>;
 }
 
 #[derive(Clone, Debug)]
@@ -47,18 +49,10 @@ pub struct FastStrategyMonster {
 }
 
 impl Strategy for FastStrategy {
-  fn choose_choice(&self, state: &CombatState) -> Vec<Choice> {
+  fn choose_choice(&self, state: &CombatState, rng: &mut Xoshiro256StarStar) -> Vec<Choice> {
     let legal_choices = state.legal_choices();
     
-    let incoming_damage = state.monsters.iter().enumerate().map (| (index, monster) | {
-      if monster.gone {0} else {
-        crate::simulation_state::monsters::intent_actions (state, index).into_iter().map (| action | {
-          if let DynAction::DamageAction (action) = action {
-            action.info.output
-          } else {0}
-        }).sum::<i32>()
-      }
-    }).sum::<i32>() - state.player.creature.block;
+    let incoming_damage = state.incoming_damage() - state.player.creature.block;
 
     vec![legal_choices
       .into_iter()
@@ -151,7 +145,7 @@ impl FastStrategy {
 pub struct SomethingStrategy {}
 
 impl Strategy for SomethingStrategy {
-  fn choose_choice(&self, state: &CombatState) -> Vec<Choice> {
+  fn choose_choice(&self, state: &CombatState, rng: &mut Xoshiro256StarStar) -> Vec<Choice> {
     /*let legal_choices = state.legal_choices();
 
     if legal_choices.len() == 1 || rand::thread_rng().gen_bool(0.00001) {
@@ -165,7 +159,7 @@ impl Strategy for SomethingStrategy {
 
     let combos = collect_starting_points(state.clone(), 200);
     let choices = combos.into_iter().map(|(mut state, choices)| {
-      run_until_unable(&mut Runner::new(&mut state, true, false));
+      run_until_unable(&mut Runner::with_seed(&mut state, true, false, rng.gen()));
       let score = self.evaluate(&state);
       (choices, score)
     });
@@ -215,9 +209,9 @@ pub fn collect_starting_points(
         assert!(new_state.fresh_subaction_queue.is_empty());
         if (results.len() + frontier.len()) < max_results
           && !new_state.combat_over()
-          && new_state.stale_subaction_stack.is_empty()
+          && (new_state.stale_subaction_stack.is_empty() || new_state.pending_choice().is_some())
         {
-          assert!(new_state.actions.is_empty());
+          assert!(new_state.actions.is_empty() || new_state.pending_choice().is_some());
           frontier.push_back((new_state, new_history));
         } else {
           results.push((new_state, new_history));
@@ -311,11 +305,14 @@ impl StartingPoint {
 pub fn play_out<S: Strategy>(runner: &mut Runner, strategy: &S) {
   run_until_unable(runner);
   while !runner.state().combat_over() {
-    let choices = strategy.choose_choice(runner.state());
+    let (state, rng) = runner.state_and_rng();
+    let choices = strategy.choose_choice(state, rng);
     for choice in choices {
+      // a card like True Grit+ leaves its choice pending, with the rest of the card's actions still queued
+      let pending = runner.state().pending_choice().is_some();
       assert!(runner.state().fresh_subaction_queue.is_empty());
-      assert!(runner.state().stale_subaction_stack.is_empty());
-      assert!(runner.state().actions.is_empty());
+      assert!(runner.state().stale_subaction_stack.is_empty() || pending);
+      assert!(runner.state().actions.is_empty() || pending);
       runner.action_now(&choice);
       run_until_unable(runner);
     }
@@ -350,3 +347,289 @@ impl CombatResult {
     }
   }
 }
+
+/// Aggregated results of many independent playouts from the same starting state.
+///
+/// Each playout only depends on its own seed, so the playouts can be split across threads
+/// (e.g. with rayon's `fold`/`reduce`) and the partial statistics combined with `merge`.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct RolloutStatistics {
+  pub rollouts: usize,
+  pub wins: usize,
+  pub total_hitpoints_left: i64,
+  pub total_turns: i64,
+}
+
+impl RolloutStatistics {
+  pub fn record(&mut self, start: &CombatState, end: &CombatState) {
+    let result = CombatResult::new(end);
+    self.rollouts += 1;
+    if result.hitpoints_left > 0 {
+      self.wins += 1;
+    }
+    self.total_hitpoints_left += result.hitpoints_left as i64;
+    self.total_turns += (end.turn_number - start.turn_number) as i64;
+  }
+
+  pub fn merge(&mut self, other: &RolloutStatistics) {
+    self.rollouts += other.rollouts;
+    self.wins += other.wins;
+    self.total_hitpoints_left += other.total_hitpoints_left;
+    self.total_turns += other.total_turns;
+  }
+
+  pub fn win_rate(&self) -> f64 {
+    self.wins as f64 / self.rollouts as f64
+  }
+  pub fn average_hitpoints_left(&self) -> f64 {
+    self.total_hitpoints_left as f64 / self.rollouts as f64
+  }
+  pub fn average_turns(&self) -> f64 {
+    self.total_turns as f64 / self.rollouts as f64
+  }
+}
+
+pub fn rollout_seed(master_seed: u64, rollout_index: usize) -> u64 {
+  // spread consecutive indices across the seed space; seed_from_u64 does further mixing on top of this
+  master_seed ^ (rollout_index as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+}
+
+/// Plays out a copy of `state`, stored in `scratch` so that repeated playouts can reuse its allocations.
+pub fn seeded_play_out_into<S: Strategy>(
+  scratch: &mut CombatState,
+  state: &CombatState,
+  strategy: &S,
+  seed: u64,
+) {
+  scratch.clone_from(state);
+  play_out(&mut Runner::with_seed(scratch, true, false, seed), strategy);
+}
+
+pub fn seeded_play_out<S: Strategy>(state: &CombatState, strategy: &S, seed: u64) -> CombatState {
+  let mut result = state.clone();
+  play_out(&mut Runner::with_seed(&mut result, true, false, seed), strategy);
+  result
+}
+
+pub fn batch_rollouts<S: Strategy>(
+  state: &CombatState,
+  strategy: &S,
+  rollouts: usize,
+  master_seed: u64,
+) -> RolloutStatistics {
+  let mut statistics = RolloutStatistics::default();
+  let mut scratch = state.clone();
+  for rollout_index in 0..rollouts {
+    seeded_play_out_into(&mut scratch, state, strategy, rollout_seed(master_seed, rollout_index));
+    statistics.record(state, &scratch);
+  }
+  statistics
+}
+
+/// Average score of `rollouts` playouts from `state` that pick uniformly random legal choices, stopping
+/// after `depth` choices if combat isn't over by then. A playout scores the player's remaining hitpoints
+/// minus the damage they took, so comparing the value after each of `legal_choices` ranks them.
+pub fn monte_carlo_value(state: &CombatState, rollouts: usize, depth: usize) -> f64 {
+  monte_carlo_value_seeded(state, rollouts, depth, rand::random())
+}
+
+/// Like `monte_carlo_value`, but each playout is seeded from `seed` like in `batch_rollouts`,
+/// so the same seed gives the same value.
+pub fn monte_carlo_value_seeded(state: &CombatState, rollouts: usize, depth: usize, seed: u64) -> f64 {
+  if rollouts == 0 {
+    return 0.0;
+  }
+  let mut total = 0.0;
+  let mut scratch = state.clone();
+  for rollout_index in 0..rollouts {
+    scratch.clone_from(state);
+    let mut runner = Runner::with_seed(&mut scratch, true, false, rollout_seed(seed, rollout_index));
+    run_until_unable(&mut runner);
+    for _ in 0..depth {
+      if runner.state().combat_over() {
+        break;
+      }
+      let (current, rng) = runner.state_and_rng();
+      let choice = current.legal_choices().choose(rng).cloned();
+      match choice {
+        Some(choice) => {
+          runner.action_now(&choice);
+          run_until_unable(&mut runner);
+        }
+        None => break,
+      }
+    }
+    let hitpoints = scratch.player.creature.hitpoints.max(0);
+    let damage_taken = (state.player.creature.hitpoints - hitpoints).max(0);
+    total += (hitpoints - damage_taken) as f64;
+  }
+  total / rollouts as f64
+}
+
+fn expectimax_leaf_value(state: &CombatState) -> f64 {
+  if state.combat_over() {
+    CombatResult::new(state).score
+  } else {
+    // unfinished combats rank between losses and wins, by the same tiebreakers CombatResult uses
+    0.5 + state.player.creature.hitpoints as f64 * 0.0001
+      - state
+        .monsters
+        .iter()
+        .filter(|monster| !monster.gone)
+        .map(|monster| monster.creature.hitpoints)
+        .sum::<i32>() as f64
+        * 0.000001
+  }
+}
+
+fn expectimax_value(state: &CombatState, depth: usize, cache: &mut HashMap<(u64, usize), f64>) -> f64 {
+  if depth == 0 || state.combat_over() {
+    return expectimax_leaf_value(state);
+  }
+  let key = (state.canonical_hash(), depth);
+  if let Some(&value) = cache.get(&key) {
+    return value;
+  }
+
+  let random_action = state
+    .stale_subaction_stack
+    .last()
+    .and_then(|action| match action.determinism(state) {
+      Determinism::Random(distribution) => Some((action.clone(), distribution)),
+      _ => None,
+    });
+  let value = if let Some((action, distribution)) = random_action {
+    let total_weight: f64 = distribution.0.iter().map(|(weight, _)| weight).sum();
+    let mut scratch = state.clone();
+    let mut value = 0.0;
+    for &(weight, random_value) in &distribution.0 {
+      scratch.clone_from(state);
+      scratch.stale_subaction_stack.pop();
+      let mut runner = Runner::new(&mut scratch, false, false);
+      runner.apply_outcome(&action, random_value);
+      run_until_unable(&mut runner);
+      value += weight / total_weight * expectimax_value(&scratch, depth, cache);
+    }
+    value
+  } else {
+    let mut scratch = state.clone();
+    let mut best = f64::NEG_INFINITY;
+    for choice in state.legal_choices() {
+      apply_choice_into(&mut scratch, state, &choice, false);
+      best = best.max(expectimax_value(&scratch, depth - 1, cache));
+    }
+    best
+  };
+
+  cache.insert(key, value);
+  value
+}
+
+/// Exhaustive search over the next `depth` choices, taking the best choice at each choice point and the
+/// probability-weighted average over the outcomes of random actions. Positions reached more than once
+/// (e.g. by playing the same cards in a different order) are only searched once.
+pub fn best_choice(state: &CombatState, depth: usize) -> (Choice, f64) {
+  let mut cache = HashMap::new();
+  let mut scratch = state.clone();
+  let mut best = None;
+  for choice in state.legal_choices() {
+    apply_choice_into(&mut scratch, state, &choice, false);
+    let value = expectimax_value(&scratch, depth.saturating_sub(1), &mut cache);
+    if best.as_ref().map_or(true, |&(_, best_value)| value > best_value) {
+      best = Some((choice, value));
+    }
+  }
+  best.expect("best_choice called on a state with no legal choices")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::simulation_state::testing::*;
+
+  struct RandomStrategy;
+
+  impl Strategy for RandomStrategy {
+    fn choose_choice(&self, state: &CombatState, rng: &mut Xoshiro256StarStar) -> Vec<Choice> {
+      vec![state.legal_choices().choose(rng).unwrap().clone()]
+    }
+  }
+
+  fn jaw_worm_combat() -> CombatState {
+    let mut state = combat(vec![monster(MonsterId::JawWorm, 42, vec![1])], Vec::new());
+    state.draw_pile = (0..5)
+      .map(|_| card(CardId::StrikeR))
+      .chain((0..5).map(|_| card(CardId::DefendR)))
+      .collect();
+    state
+  }
+
+  #[test]
+  fn same_seed_gives_same_statistics() {
+    let state = jaw_worm_combat();
+    let first = batch_rollouts(&state, &RandomStrategy, 20, 7);
+    let second = batch_rollouts(&state, &RandomStrategy, 20, 7);
+    assert_eq!(first, second);
+    assert_eq!(first.rollouts, 20);
+    assert!(first.total_turns > 0);
+  }
+
+  #[test]
+  fn monte_carlo_value_is_reproducible_from_the_seed() {
+    let state = jaw_worm_combat();
+    assert_eq!(
+      monte_carlo_value_seeded(&state, 10, 20, 5),
+      monte_carlo_value_seeded(&state, 10, 20, 5)
+    );
+    assert_eq!(monte_carlo_value(&state, 0, 20), 0.0);
+  }
+
+  #[test]
+  fn rollouts_resolve_pending_choices() {
+    let mut state = jaw_worm_combat();
+    state.hand = vec![upgraded(CardId::TrueGrit), card(CardId::StrikeR), card(CardId::DefendR)]
+      .into_iter()
+      .collect();
+    play(&mut state, CardId::TrueGrit, 0);
+    assert!(state.pending_choice().is_some());
+    assert!(state.player_in_control());
+    assert_eq!(state.legal_choices().len(), 2);
+
+    let starting_points = collect_starting_points(state.clone(), 50);
+    assert!(starting_points
+      .iter()
+      .all(|(_, choices)| matches!(choices[0], DynAction::ChooseCard(_))));
+
+    let end = seeded_play_out(&state, &RandomStrategy, 3);
+    assert!(end.combat_over());
+    assert_eq!(end.exhaust_pile.len(), 1);
+  }
+
+  #[test]
+  fn best_choice_takes_lethal() {
+    let state = combat(
+      vec![monster(MonsterId::Cultist, 6, vec![1])],
+      vec![card(CardId::StrikeR), card(CardId::DefendR)],
+    );
+    let (choice, _) = best_choice(&state, 1);
+    match choice {
+      DynAction::PlayCard(PlayCard { card, target }) => {
+        assert_eq!(card.card_info.id, CardId::StrikeR);
+        assert_eq!(target, 0);
+      }
+      other => panic!("expected Strike, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn rollouts_against_a_weak_monster_are_mostly_wins() {
+    let mut state = combat(vec![monster(MonsterId::Cultist, 10, vec![3])], Vec::new());
+    state.draw_pile = (0..8)
+      .map(|_| card(CardId::StrikeR))
+      .chain((0..2).map(|_| card(CardId::DefendR)))
+      .collect();
+    let statistics = batch_rollouts(&state, &RandomStrategy, 1000, 11);
+    assert_eq!(statistics.rollouts, 1000);
+    assert!(statistics.win_rate() > 0.9);
+  }
+}